@@ -4,24 +4,43 @@
 pub mod modules;
 
 // Пересклады основных типов для удобства
-pub use modules::{Lexer, Parser, CodeGenerator, TopLevel, DataType, Expr, Statement, FunctionDef, RouteDef, TokenType};
+pub use modules::{Diagnostic, Severity, Lexer, Parser, ParseResult, CodeGenerator, Span, LexError, ParseError, TranslateError, TopLevel, DataType, Assoc, BinOp, Expr, ExprKind, UnaryOp, InterpolationPart, Literal, Statement, StatementKind, ForIter, FunctionDef, RouteDef, ModelDef, FieldDef, EnumDef, VariantDef, ErrorCodeDef, TypeAliasDef, CatchClause, MatchArm, ArmBody, Pattern, TokenType};
 
 // Версия
 pub const VERSION: &str = "0.2.0";
 
 /// Транслирует код Krait в Rust
-pub fn translate(source: &str) -> Result<String, String> {
+pub fn translate(source: &str) -> Result<String, TranslateError> {
+    translate_with_modules(source, &[])
+}
+
+/// Транслирует код Krait в Rust; `project_modules` — имена соседних файлов `.kr`,
+/// которые можно импортировать как модули проекта
+pub fn translate_with_modules(source: &str, project_modules: &[String]) -> Result<String, TranslateError> {
     // Лексический анализ
     let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize();
+    let mut diagnostics: Vec<Diagnostic> = lexer.errors().iter().cloned().map(Diagnostic::from).collect();
     
     // Синтаксический анализ
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse()?;
+    let mut parsed = parser.parse();
+    diagnostics.extend(std::mem::take(&mut parsed.diagnostics));
+    
+    // Семантические проверки имеют смысл только для полностью разобранного кода;
+    // проверкам нужны уже разрешённые имена типов
+    if !diagnostics.iter().any(|d| d.is_error()) {
+        diagnostics.extend(modules::codegen::resolve::resolve(&mut parsed.items));
+        diagnostics.extend(modules::codegen::check::check(&parsed.items));
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        diagnostics.sort_by_key(|d| d.span.start);
+        return Err(TranslateError::Diagnostics(diagnostics));
+    }
     
     // Генерация кода
-    let mut codegen = CodeGenerator::new();
-    let rust_code = codegen.generate(&ast[..]);
+    let mut codegen = CodeGenerator::new().with_project_modules(project_modules.iter().cloned());
+    let rust_code = codegen.generate(&parsed.items);
     
     Ok(rust_code)
 }
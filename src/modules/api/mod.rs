@@ -3,22 +3,13 @@
 use super::codegen::parser::RouteDef;
 
 /// Информация о HTTP API
+#[derive(Default)]
 pub struct ApiInfo {
     pub has_routes: bool,
     pub routes_count: usize,
     pub http_methods: Vec<String>,
 }
 
-impl Default for ApiInfo {
-    fn default() -> Self {
-        ApiInfo {
-            has_routes: false,
-            routes_count: 0,
-            http_methods: Vec::new(),
-        }
-    }
-}
-
 impl ApiInfo {
     /// Анализирует роуты и собирает информацию
     pub fn from_routes(routes: &[&RouteDef]) -> Self {
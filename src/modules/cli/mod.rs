@@ -1,5 +1,7 @@
 // CLI модуль для работы с командной строкой
 
+use super::diagnostics::Renderer;
+use crate::TranslateError;
 use std::fs;
 use std::path::Path;
 
@@ -18,6 +20,28 @@ impl CliResult {
     }
 }
 
+/// Печатает ошибки трансляции в stderr с фрагментами исходника
+/// и возвращает краткое описание для итогового сообщения
+fn report_translate_error(input_path: &str, source: &str, err: &TranslateError) -> String {
+    let diagnostics = err.diagnostics();
+    eprint!("{}", Renderer::new(input_path, source).render_all(diagnostics));
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    format!("{}: ошибок — {}", input_path, errors)
+}
+
+/// Имена файлов `.kr` в директории: модули проекта, доступные для `import`
+fn project_modules(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("kr"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .collect()
+}
+
 /// Транслирует одиночный файл Krait в Rust
 pub fn translate_file(input_path: &str, output_path: &str) -> CliResult {
     // Читаем исходный файл
@@ -33,10 +57,12 @@ pub fn translate_file(input_path: &str, output_path: &str) -> CliResult {
     println!("📏 Строк: {}", source.lines().count());
     
     // Транслируем код
-    let rust_code = match crate::translate(&source) {
+    let dir = Path::new(input_path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let rust_code = match crate::translate_with_modules(&source, &project_modules(dir)) {
         Ok(code) => code,
         Err(e) => {
-            return CliResult::Error(format!("Ошибка синтаксического анализа: {}", e));
+            let summary = report_translate_error(input_path, &source, &e);
+            return CliResult::Error(format!("Ошибка трансляции: {}", summary));
         }
     };
     
@@ -66,23 +92,21 @@ pub fn translate_project(input_dir: &str, output_dir: &str) -> CliResult {
     
     match fs::read_dir(input_dir) {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.extension().and_then(|s| s.to_str()) == Some("kr") {
-                        let input_path = path.to_string_lossy().to_string();
-                        let filename = path.file_stem().unwrap().to_string_lossy();
-                        let output_path = format!("{}/{}.rs", output_dir, filename);
-                        
-                        match translate_file(&input_path, &output_path) {
-                            CliResult::Success(msg) => {
-                                println!("  ✓ {}", msg);
-                                translated_count += 1;
-                            }
-                            CliResult::Error(err) => {
-                                println!("  ✗ {}", err);
-                                error_count += 1;
-                            }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("kr") {
+                    let input_path = path.to_string_lossy().to_string();
+                    let filename = path.file_stem().unwrap().to_string_lossy();
+                    let output_path = format!("{}/{}.rs", output_dir, filename);
+                    
+                    match translate_file(&input_path, &output_path) {
+                        CliResult::Success(msg) => {
+                            println!("  ✓ {}", msg);
+                            translated_count += 1;
+                        }
+                        CliResult::Error(err) => {
+                            println!("  ✗ {}", err);
+                            error_count += 1;
                         }
                     }
                 }
@@ -120,46 +144,46 @@ pub fn build_project() -> CliResult {
     
     println!("📦 Step 1: Translating Krait files...\n");
     
+    let modules = project_modules(Path::new("krait_src"));
     match fs::read_dir("krait_src") {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.extension().and_then(|s| s.to_str()) == Some("kr") {
-                        let input_path = path.to_string_lossy().to_string();
-                        let filename = path.file_stem().unwrap().to_string_lossy();
-                        let output_path = format!("rust_code/{}.rs", filename);
-                        
-                        // Читаем исходный файл
-                        let source = match fs::read_to_string(&input_path) {
-                            Ok(content) => content,
-                            Err(e) => {
-                                eprintln!("  ✗ Ошибка чтения {}: {}", input_path, e);
-                                error_count += 1;
-                                continue;
-                            }
-                        };
-                        
-                        // Транслируем код
-                        let rust_code = match crate::translate(&source) {
-                            Ok(code) => code,
-                            Err(e) => {
-                                eprintln!("  ✗ Ошибка трансляции {}: {}", input_path, e);
-                                error_count += 1;
-                                continue;
-                            }
-                        };
-                        
-                        // Пишем в выходной файл
-                        if let Err(e) = fs::write(&output_path, &rust_code) {
-                            eprintln!("  ✗ Ошибка записи {}: {}", output_path, e);
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("kr") {
+                    let input_path = path.to_string_lossy().to_string();
+                    let filename = path.file_stem().unwrap().to_string_lossy();
+                    let output_path = format!("rust_code/{}.rs", filename);
+                    
+                    // Читаем исходный файл
+                    let source = match fs::read_to_string(&input_path) {
+                        Ok(content) => content,
+                        Err(e) => {
+                            eprintln!("  ✗ Ошибка чтения {}: {}", input_path, e);
+                            error_count += 1;
+                            continue;
+                        }
+                    };
+                    
+                    // Транслируем код
+                    let rust_code = match crate::translate_with_modules(&source, &modules) {
+                        Ok(code) => code,
+                        Err(e) => {
+                            let summary = report_translate_error(&input_path, &source, &e);
+                            eprintln!("  ✗ Ошибка трансляции {}", summary);
                             error_count += 1;
                             continue;
                         }
-                        
-                        println!("  ✓ {}.kr → {}.rs", filename, filename);
-                        translated_count += 1;
+                    };
+                    
+                    // Пишем в выходной файл
+                    if let Err(e) = fs::write(&output_path, &rust_code) {
+                        eprintln!("  ✗ Ошибка записи {}: {}", output_path, e);
+                        error_count += 1;
+                        continue;
                     }
+                    
+                    println!("  ✓ {}.kr → {}.rs", filename, filename);
+                    translated_count += 1;
                 }
             }
         }
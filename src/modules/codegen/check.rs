@@ -0,0 +1,1137 @@
+// Семантические проверки AST перед генерацией кода
+//
+// Проверки ловят то, что иначе всплыло бы только как ошибка rustc
+// в сгенерированном коде, и сообщают о них в терминах исходника Krait.
+
+use super::parser::{
+    ArmBody, BinOp, CatchClause, DataType, EnumDef, ErrorCodeDef, Expr, ExprKind, ForIter, FunctionDef, InterpolationPart,
+    Literal, MatchArm, ModelDef, Pattern, Statement, StatementKind, TopLevel, UnaryOp,
+};
+use super::span::Span;
+use crate::modules::diagnostics::Diagnostic;
+use std::collections::HashMap;
+
+/// Проверяет разобранную программу и возвращает найденные ошибки
+pub fn check(items: &[TopLevel]) -> Vec<Diagnostic> {
+    let mut checker = Checker::new(items);
+    let types = items.iter().filter_map(|item| match item {
+        TopLevel::Model(model) => Some((model.name.as_str(), model.span)),
+        TopLevel::Enum(enum_def) => Some((enum_def.name.as_str(), enum_def.span)),
+        _ => None,
+    });
+    checker.check_duplicates("type", types);
+    for item in items {
+        checker.check_item(item);
+    }
+    checker.diagnostics
+}
+
+/// Числовой тип Rust, в который попадает литерал
+struct NumericType {
+    name: &'static str,
+    is_float: bool,
+    max: u128,
+}
+
+impl NumericType {
+    fn from_rust(name: &str) -> Option<NumericType> {
+        let (name, is_float, max): (&'static str, bool, u128) = match name {
+            "i8" => ("i8", false, i8::MAX as u128),
+            "i16" => ("i16", false, i16::MAX as u128),
+            "i32" => ("i32", false, i32::MAX as u128),
+            "i64" => ("i64", false, i64::MAX as u128),
+            "i128" => ("i128", false, i128::MAX as u128),
+            "isize" => ("isize", false, i64::MAX as u128),
+            "u8" => ("u8", false, u8::MAX as u128),
+            "u16" => ("u16", false, u16::MAX as u128),
+            "u32" => ("u32", false, u32::MAX as u128),
+            "u64" => ("u64", false, u64::MAX as u128),
+            "u128" => ("u128", false, u128::MAX),
+            "usize" => ("usize", false, u64::MAX as u128),
+            "f32" => ("f32", true, 0),
+            "f64" => ("f64", true, 0),
+            _ => return None,
+        };
+        Some(NumericType { name, is_float, max })
+    }
+
+    /// Числовой тип Krait; `auto` и нечисловые типы литералы не ограничивают
+    fn from_data_type(ty: &DataType) -> Option<NumericType> {
+        match ty {
+            DataType::Int | DataType::Float | DataType::Double => Self::from_rust(&ty.to_rust()),
+            _ => None,
+        }
+    }
+
+    fn fits_float(&self, value: f64) -> bool {
+        match self.name {
+            "f32" => value.abs() <= f32::MAX as f64,
+            _ => value.is_finite(),
+        }
+    }
+}
+
+struct Checker<'a> {
+    functions: HashMap<&'a str, &'a FunctionDef>,
+    models: HashMap<&'a str, &'a ModelDef>,
+    enums: HashMap<&'a str, &'a EnumDef>,
+    error_codes: HashMap<&'a str, &'a ErrorCodeDef>,
+    /// Объявленные типы видимых переменных: по ним проверяются присваивания
+    variables: HashMap<&'a str, &'a DataType>,
+    return_type: Option<&'a DataType>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(items: &'a [TopLevel]) -> Self {
+        let functions = items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::Function(func) => Some((func.name.as_str(), func)),
+                _ => None,
+            })
+            .collect();
+        let models = items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::Model(model) => Some((model.name.as_str(), model)),
+                _ => None,
+            })
+            .collect();
+        let enums = items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::Enum(enum_def) => Some((enum_def.name.as_str(), enum_def)),
+                _ => None,
+            })
+            .collect();
+        let error_codes = items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::ErrorCode(error_code) => Some((error_code.name.as_str(), error_code)),
+                _ => None,
+            })
+            .collect();
+        Checker {
+            functions,
+            models,
+            enums,
+            error_codes,
+            variables: HashMap::new(),
+            return_type: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn check_item(&mut self, item: &'a TopLevel) {
+        match item {
+            TopLevel::Function(func) => {
+                let outer = self.variables.clone();
+                self.variables.extend(func.params.iter().map(|(name, ty)| (name.as_str(), ty)));
+                self.return_type = Some(&func.return_type);
+                self.check_block(&func.body);
+                self.return_type = None;
+                self.variables = outer;
+            }
+            TopLevel::Route(route) => self.check_block(&route.body),
+            TopLevel::Model(model) => {
+                self.check_duplicates("field", model.fields.iter().map(|field| (field.name.as_str(), field.span)));
+                for field in &model.fields {
+                    match &field.default {
+                        Some(default) => {
+                            self.check_expr(default);
+                            self.check_target(default, &field.field_type);
+                        }
+                        // `#[serde(default)]` и `..Default::default()` требуют `Default` у каждого поля
+                        None if !self.has_default(&field.field_type) => {
+                            let diag = Diagnostic::error(
+                                "K0216",
+                                format!("field `{}` of type `{}` needs a default value", field.name, field.field_type),
+                                field.span,
+                            )
+                            .with_help(format!(
+                                "write one, such as `{} {} = {}`",
+                                field.field_type,
+                                field.name,
+                                self.default_hint(&field.field_type)
+                            ));
+                            self.diagnostics.push(diag);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            TopLevel::Statement(stmt) => {
+                self.check_documented_constant(stmt);
+                self.check_statement(stmt);
+            }
+            TopLevel::Enum(enum_def) => {
+                self.check_duplicates("variant", enum_def.variants.iter().map(|variant| (variant.name.as_str(), variant.span)));
+            }
+            TopLevel::ErrorCode(_) | TopLevel::TypeAlias(_) | TopLevel::Import { .. } | TopLevel::Error(_) => {}
+        }
+    }
+
+    fn check_block(&mut self, body: &'a [Statement]) {
+        let outer = self.variables.clone();
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.variables = outer;
+    }
+
+    fn check_statement(&mut self, stmt: &'a Statement) {
+        match &stmt.kind {
+            StatementKind::VarDecl { name, var_type, value, .. } => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                    self.check_target(value, var_type);
+                }
+                self.variables.insert(name, var_type);
+            }
+            StatementKind::Return(Some(value)) => {
+                self.check_expr(value);
+                if let Some(ty) = self.return_type {
+                    self.check_target(value, ty);
+                }
+            }
+            StatementKind::Return(None) | StatementKind::Break(_) | StatementKind::Continue(_) | StatementKind::Error => {}
+            StatementKind::If { branches, else_body } => {
+                for (condition, body) in branches {
+                    self.check_expr(condition);
+                    self.check_block(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            StatementKind::While { condition, body, .. } => {
+                self.check_expr(condition);
+                self.check_block(body);
+            }
+            StatementKind::For { iter: ForIter::Range { start, end, step, .. }, body, .. } => {
+                self.check_expr(start);
+                self.check_expr(end);
+                if let Some(step) = step {
+                    self.check_expr(step);
+                    self.check_step(step);
+                }
+                self.check_block(body);
+            }
+            StatementKind::For { iter: ForIter::Each { collection, .. }, body, .. } => {
+                self.check_expr(collection);
+                self.check_block(body);
+            }
+            StatementKind::Try { body, catches, finally } => {
+                self.check_block(body);
+                self.check_catches(catches);
+                if let Some(finally) = finally {
+                    self.check_block(finally);
+                }
+            }
+            StatementKind::ExprStmt(expr) => self.check_expr(expr),
+            StatementKind::Assign { target, value, .. } => {
+                self.check_expr(target);
+                self.check_expr(value);
+                // `x = v` и `x += v` подставляют литерал в тип `x`, как объявление
+                if let ExprKind::Identifier(name) = &target.kind {
+                    if let Some(ty) = self.variables.get(name.as_str()).copied() {
+                        self.check_target(value, ty);
+                    }
+                }
+            }
+            StatementKind::Match { scrutinee, arms } => self.check_match(scrutinee, arms),
+            StatementKind::Raise(name) => self.check_error_code(name, stmt.span),
+        }
+    }
+
+    fn check_error_code(&mut self, name: &str, span: Span) {
+        if self.error_codes.contains_key(name) {
+            return;
+        }
+        let diag = Diagnostic::error("K0211", format!("cannot find error code `{}`", name), span);
+        let diag = match closest(name, self.error_codes.keys().copied()) {
+            Some(similar) => diag.with_help(format!("an error code with a similar name exists: `{}`", similar)),
+            None => diag.with_help(format!("declare it at the top level: `error_code {} = 500`", name)),
+        };
+        self.diagnostics.push(diag);
+    }
+
+    /// Ветки `catch`: коды объявлены, и каждую ветку может достичь хоть одна ошибка
+    fn check_catches(&mut self, catches: &'a [CatchClause]) {
+        let mut catch_all: Option<&CatchClause> = None;
+        let mut seen: Vec<&str> = Vec::new();
+        for clause in catches {
+            if let Some(code) = &clause.code {
+                self.check_error_code(code, clause.span);
+            }
+            let shadowed_by = match &clause.code {
+                _ if catch_all.is_some() => catch_all.map(|clause| clause.span),
+                Some(code) if seen.contains(&code.as_str()) => catches
+                    .iter()
+                    .find(|other| other.code.as_deref() == Some(code))
+                    .map(|other| other.span),
+                _ => None,
+            };
+            if let Some(earlier) = shadowed_by {
+                self.diagnostics.push(
+                    Diagnostic::error("K0212", "unreachable `catch` clause", clause.span)
+                        .with_note(format!("errors reaching it are already caught on line {}", earlier.line)),
+                );
+            }
+            match &clause.code {
+                Some(code) => seen.push(code),
+                None => catch_all = catch_all.or(Some(clause)),
+            }
+            self.check_block(&clause.body);
+        }
+    }
+
+    fn check_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.check_suffix_range(literal, false, expr),
+            ExprKind::Unary { op: UnaryOp::Neg, operand } if matches!(operand.kind, ExprKind::Literal(_)) => {
+                if let ExprKind::Literal(literal) = &operand.kind {
+                    self.check_suffix_range(literal, true, expr);
+                }
+            }
+            ExprKind::BinaryOp { left, op: BinOp::Coalesce, right } => {
+                self.check_expr(left);
+                self.check_expr(right);
+                self.check_coalesce(left);
+            }
+            ExprKind::BinaryOp { left, right, .. } => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+            ExprKind::Unary { operand, .. } => self.check_expr(operand),
+            ExprKind::List(items) => {
+                for item in items {
+                    self.check_expr(item);
+                }
+            }
+            ExprKind::Index { target, index } => {
+                self.check_expr(target);
+                self.check_expr(index);
+            }
+            ExprKind::Field { target, name } => match self.enum_of(target) {
+                Some(enum_def) => self.check_variant(enum_def, name, &[], expr),
+                None => self.check_expr(target),
+            },
+            ExprKind::MethodCall { target, name, args } if self.enum_of(target).is_some() => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                if let Some(enum_def) = self.enum_of(target) {
+                    self.check_variant(enum_def, name, args, expr);
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => self.check_match(scrutinee, arms),
+            ExprKind::MethodCall { target, args, .. } => {
+                self.check_expr(target);
+                for arg in args {
+                    self.check_expr(arg);
+                }
+            }
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.check_expr(key);
+                    self.check_expr(value);
+                }
+            }
+            ExprKind::FunctionCall { name, args } => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                if let Some(func) = self.functions.get(name.as_str()) {
+                    for (arg, (_, ty)) in args.iter().zip(&func.params) {
+                        self.check_target(arg, ty);
+                    }
+                }
+            }
+            ExprKind::Construct { model, fields } => {
+                for (_, value) in fields {
+                    self.check_expr(value);
+                }
+                self.check_construct(model, fields, expr);
+            }
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(value) = part {
+                        self.check_expr(value);
+                    }
+                }
+            }
+            ExprKind::Identifier(_) | ExprKind::Nil | ExprKind::Error => {}
+        }
+    }
+
+    /// Перечисление, если `target` — его имя
+    fn enum_of(&self, target: &Expr) -> Option<&'a EnumDef> {
+        match &target.kind {
+            ExprKind::Identifier(name) => self.enums.get(name.as_str()).copied(),
+            _ => None,
+        }
+    }
+    
+    /// Значение варианта `Status.Banned("spam", 3)`: вариант существует,
+    /// аргументов столько же, сколько полей
+    fn check_variant(&mut self, enum_def: &EnumDef, name: &str, args: &[Expr], expr: &Expr) {
+        let Some(variant) = enum_def.variant(name) else {
+            self.diagnostics.push(self.unknown_variant(enum_def, name, expr.span));
+            return;
+        };
+        if args.len() != variant.fields.len() {
+            let fields: Vec<&str> = variant.fields.iter().map(|(field, _)| field.as_str()).collect();
+            let mut diag = Diagnostic::error(
+                "K0208",
+                format!(
+                    "variant `{}.{}` has {} field{}, but {} {} given",
+                    enum_def.name,
+                    name,
+                    fields.len(),
+                    if fields.len() == 1 { "" } else { "s" },
+                    args.len(),
+                    if args.len() == 1 { "was" } else { "were" },
+                ),
+                expr.span,
+            )
+            .with_label(variant.span, "variant declared here");
+            if !fields.is_empty() {
+                diag = diag.with_help(format!("pass the fields in order: `{}.{}({})`", enum_def.name, name, fields.join(", ")));
+            }
+            self.diagnostics.push(diag);
+            return;
+        }
+        for (arg, (_, ty)) in args.iter().zip(&variant.fields) {
+            self.check_target(arg, ty);
+        }
+    }
+    
+    fn unknown_variant(&self, enum_def: &EnumDef, name: &str, span: Span) -> Diagnostic {
+        let diag = Diagnostic::error("K0207", format!("no variant named `{}` in enum `{}`", name, enum_def.name), span)
+            .with_label(enum_def.span, format!("`{}` declared here", enum_def.name));
+        match closest(name, enum_def.variants.iter().map(|variant| variant.name.as_str())) {
+            Some(similar) => diag.with_help(format!("a variant with a similar name exists: `{}`", similar)),
+            None => diag,
+        }
+    }
+    
+    fn unknown_enum(&self, name: &str, span: Span) -> Diagnostic {
+        let diag = Diagnostic::error("K0207", format!("cannot find enum `{}`", name), span);
+        match closest(name, self.enums.keys().copied()) {
+            Some(similar) => diag.with_help(format!("an enum with a similar name exists: `{}`", similar)),
+            None => diag,
+        }
+    }
+    
+    /// Ветки `match`: образцы относятся к одному перечислению и вместе
+    /// покрывают все возможные значения
+    fn check_match(&mut self, scrutinee: &'a Expr, arms: &'a [MatchArm]) {
+        self.check_expr(scrutinee);
+        for arm in arms {
+            match &arm.body {
+                ArmBody::Value(value) => self.check_expr(value),
+                ArmBody::Block(body) => self.check_block(body),
+            }
+        }
+        
+        let errors = self.diagnostics.len();
+        let Some(enum_def) = self.match_enum(arms) else {
+            if self.diagnostics.len() == errors {
+                self.check_literal_coverage(scrutinee, arms);
+            }
+            return;
+        };
+        let mut covered: Vec<(&str, Span)> = Vec::new();
+        let mut wildcard: Option<Span> = None;
+        for arm in arms {
+            if let Some(earlier) = wildcard {
+                self.diagnostics.push(
+                    Diagnostic::error("K0221", "unreachable match arm", arm.span)
+                        .with_note(format!("`case _` on line {} already matches every value", earlier.line)),
+                );
+                continue;
+            }
+            match &arm.pattern {
+                Pattern::Wildcard => wildcard = Some(arm.span),
+                Pattern::Literal(_) => self.diagnostics.push(
+                    Diagnostic::error(
+                        "K0209",
+                        format!("mismatched pattern: expected a variant of `{}`, found a literal", enum_def.name),
+                        arm.span,
+                    )
+                    .with_help(format!("write the variant, such as `{}.{}`", enum_def.name, enum_def.variants.first().map_or("Name", |v| v.name.as_str()))),
+                ),
+                Pattern::Variant { enum_name: Some(other), .. } if !self.enums.contains_key(other.as_str()) => {
+                    self.diagnostics.push(self.unknown_enum(other, arm.span));
+                }
+                Pattern::Variant { enum_name: Some(other), variant, .. } if other != &enum_def.name => {
+                    self.diagnostics.push(Diagnostic::error(
+                        "K0209",
+                        format!("mismatched pattern: expected a variant of `{}`, found `{}.{}`", enum_def.name, other, variant),
+                        arm.span,
+                    ));
+                }
+                Pattern::Variant { variant, bindings, .. } => {
+                    let Some(def) = enum_def.variant(variant) else {
+                        self.diagnostics.push(self.unknown_variant(enum_def, variant, arm.span));
+                        continue;
+                    };
+                    if !bindings.is_empty() && bindings.len() != def.fields.len() {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "K0208",
+                                format!(
+                                    "pattern binds {} field{} of `{}.{}`, which has {}",
+                                    bindings.len(),
+                                    if bindings.len() == 1 { "" } else { "s" },
+                                    enum_def.name,
+                                    variant,
+                                    def.fields.len()
+                                ),
+                                arm.span,
+                            )
+                            .with_label(def.span, "variant declared here")
+                            .with_help("bind every field in order, using `_` for the ones you don't need"),
+                        );
+                    }
+                    if let Some((_, earlier)) = covered.iter().find(|(name, _)| name == variant) {
+                        self.diagnostics.push(
+                            Diagnostic::error("K0221", "unreachable match arm", arm.span).with_note(format!(
+                                "`{}.{}` is already matched on line {}",
+                                enum_def.name, variant, earlier.line
+                            )),
+                        );
+                    }
+                    covered.push((variant.as_str(), arm.span));
+                }
+            }
+        }
+        if self.diagnostics.len() != errors || arms.iter().any(|arm| matches!(arm.pattern, Pattern::Wildcard)) {
+            return;
+        }
+        let missing: Vec<String> = enum_def
+            .variants
+            .iter()
+            .filter(|variant| !covered.iter().any(|(name, _)| name == &variant.name))
+            .map(|variant| {
+                let fields = if variant.fields.is_empty() { "" } else { "(..)" };
+                format!("`{}.{}{}`", enum_def.name, variant.name, fields)
+            })
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.diagnostics.push(
+            Diagnostic::error("K0206", format!("non-exhaustive match: {} not covered", list_names(&missing)), scrutinee.span)
+                .with_label(enum_def.span, format!("`{}` declared here", enum_def.name))
+                .with_help("add a `case` for each missing variant, or `case _` for the rest"),
+        );
+    }
+    
+    /// Перечисление, по вариантам которого идёт `match`. Неизвестные
+    /// перечисления и варианты без перечисления описываются в диагностиках.
+    fn match_enum(&mut self, arms: &[MatchArm]) -> Option<&'a EnumDef> {
+        for arm in arms {
+            if let Pattern::Variant { enum_name: Some(name), .. } = &arm.pattern {
+                if let Some(enum_def) = self.enums.get(name.as_str()) {
+                    return Some(enum_def);
+                }
+                self.diagnostics.push(self.unknown_enum(name, arm.span));
+                return None;
+            }
+        }
+        for arm in arms {
+            if let Pattern::Variant { variant, .. } = &arm.pattern {
+                let mut owners: Vec<&'a EnumDef> =
+                    self.enums.values().copied().filter(|e| e.variant(variant).is_some()).collect();
+                owners.sort_by(|a, b| a.name.cmp(&b.name));
+                let diag = match owners.as_slice() {
+                    [owner] => return Some(owner),
+                    [] => Diagnostic::error("K0207", format!("cannot find variant `{}`", variant), arm.span)
+                        .with_note("patterns name enum variants; use `_` to match any value"),
+                    [first, ..] => Diagnostic::error("K0207", format!("variant `{}` is ambiguous", variant), arm.span)
+                        .with_help(format!("write the enum name: `{}.{}`", first.name, variant)),
+                };
+                self.diagnostics.push(diag);
+                return None;
+            }
+        }
+        None
+    }
+    
+    /// `match` по литералам полон только с `case _`, либо если он перебирает `true` и `false`
+    fn check_literal_coverage(&mut self, scrutinee: &Expr, arms: &[MatchArm]) {
+        if arms.iter().any(|arm| matches!(arm.pattern, Pattern::Wildcard)) {
+            return;
+        }
+        let has_bool = |value: &str| {
+            arms.iter().any(|arm| {
+                matches!(&arm.pattern, Pattern::Literal(e) if matches!(&e.kind, ExprKind::Identifier(name) if name == value))
+            })
+        };
+        let missing: Vec<String> = match (has_bool("true"), has_bool("false")) {
+            (true, true) => return,
+            (true, false) => vec!["`false`".to_string()],
+            (false, true) => vec!["`true`".to_string()],
+            (false, false) => vec!["`_`".to_string()],
+        };
+        self.diagnostics.push(
+            Diagnostic::error("K0206", format!("non-exhaustive match: {} not covered", list_names(&missing)), scrutinee.span)
+                .with_help("add a `case _` arm for the remaining values"),
+        );
+    }
+    
+    /// Создаваемая модель должна быть объявлена, а поля — принадлежать ей
+    fn check_construct(&mut self, name: &str, fields: &[(String, Expr)], expr: &Expr) {
+        let Some(model) = self.models.get(name).copied() else {
+            let mut diag = Diagnostic::error("K0204", format!("cannot find model `{}`", name), expr.span);
+            if let Some(similar) = closest(name, self.models.keys().copied()) {
+                diag = diag.with_help(format!("a model with a similar name exists: `{}`", similar));
+            }
+            self.diagnostics.push(diag);
+            return;
+        };
+        for (field_name, value) in fields {
+            match model.fields.iter().find(|field| &field.name == field_name) {
+                Some(field) => self.check_target(value, &field.field_type),
+                None => {
+                    let known: Vec<String> = model.fields.iter().map(|field| format!("`{}`", field.name)).collect();
+                    let mut diag = Diagnostic::error(
+                        "K0205",
+                        format!("model `{}` has no field named `{}`", name, field_name),
+                        value.span,
+                    )
+                    .with_label(model.span, format!("`{}` declared here", name));
+                    diag = if known.is_empty() {
+                        diag.with_note(format!("`{}` has no fields", name))
+                    } else {
+                        diag.with_note(format!("available fields are: {}", known.join(", ")))
+                    };
+                    self.diagnostics.push(diag);
+                }
+            }
+        }
+        // Пропущенные поля берутся из `Default`, а его есть не у всех типов
+        for field in &model.fields {
+            let given = fields.iter().any(|(field_name, _)| field_name == &field.name);
+            if given || field.default.is_some() || self.has_default(&field.field_type) {
+                continue;
+            }
+            let diag = Diagnostic::error("K0217", format!("missing field `{}` in `{}`", field.name, name), expr.span)
+                .with_label(field.span, format!("`{}` has no default value", field.name))
+                .with_help(format!("set it: `{} {{ {}: {} }}`", name, field.name, self.default_hint(&field.field_type)));
+            self.diagnostics.push(diag);
+        }
+    }
+
+    /// Есть ли у типа поля `Default`. У перечислений его нет; модели файла
+    /// получают его всегда (поля без него отклоняет K0216), а объявлений
+    /// типов из других модулей отсюда не видно.
+    fn has_default(&self, ty: &DataType) -> bool {
+        match ty {
+            DataType::Named { name, .. } => self.models.contains_key(name.as_str()),
+            _ => true,
+        }
+    }
+
+    /// Пример значения для подсказки: первый вариант перечисления без полей
+    fn default_hint(&self, ty: &DataType) -> String {
+        let DataType::Named { name, .. } = ty else {
+            return "...".to_string();
+        };
+        self.enums
+            .get(name.as_str())
+            .and_then(|enum_def| enum_def.variants.iter().find(|variant| variant.fields.is_empty()))
+            .map_or_else(|| "...".to_string(), |variant| format!("{}.{}", name, variant.name))
+    }
+
+    /// Переменная верхнего уровня становится константой Rust, только если это
+    /// возможно; у остальных документация пропадает вместе с переменной
+    fn check_documented_constant(&mut self, stmt: &Statement) {
+        let StatementKind::VarDecl { name, var_type, value, doc, .. } = &stmt.kind else {
+            return;
+        };
+        if doc.is_empty() || constant_type(var_type, value.as_ref()).is_some() {
+            return;
+        }
+        self.diagnostics.push(
+            Diagnostic::warning("K0218", format!("doc comment on top-level variable `{}` is ignored", name), stmt.span)
+                .with_note("only top-level variables with a literal `int`, `float`, `double` or `bool` value become Rust constants"),
+        );
+    }
+
+    /// Слева от `??` должно стоять необязательное значение: у `T` запасное значение не нужно
+    fn check_coalesce(&mut self, left: &Expr) {
+        let Some(ty) = self.declared_type(left) else {
+            return;
+        };
+        if matches!(ty, DataType::Optional(_) | DataType::Auto) {
+            return;
+        }
+        self.diagnostics.push(
+            Diagnostic::error("K0219", format!("left side of `??` has non-optional type `{}`", ty), left.span)
+                .with_help("the value is never `nil`; remove the `??` and its fallback"),
+        );
+    }
+
+    /// Объявленный тип переменной или результата функции программы
+    fn declared_type(&self, expr: &Expr) -> Option<&'a DataType> {
+        match &expr.kind {
+            ExprKind::Identifier(name) => self.variables.get(name.as_str()).copied(),
+            ExprKind::FunctionCall { name, .. } => self.functions.get(name.as_str()).map(|func| &func.return_type),
+            _ => None,
+        }
+    }
+
+    /// Повторное объявление имени: ошибка на каждом следующем, с отсылкой к первому
+    fn check_duplicates<'n>(&mut self, what: &str, names: impl Iterator<Item = (&'n str, Span)>) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for (name, span) in names {
+            match seen.get(name) {
+                Some(first) => self.diagnostics.push(
+                    Diagnostic::error("K0220", format!("the {} `{}` is defined multiple times", what, name), span)
+                        .with_label(*first, format!("previous definition of `{}` here", name)),
+                ),
+                None => {
+                    seen.insert(name, span);
+                }
+            }
+        }
+    }
+
+    /// Шаг диапазона: нулевой шаг зациклил бы `for` (в Rust `step_by(0)` — паника)
+    fn check_step(&mut self, step: &Expr) {
+        if let Some((Literal::Int { value: 0, .. }, _)) = numeric_literal(step) {
+            self.diagnostics.push(
+                Diagnostic::error("K0210", "`for` step cannot be zero", step.span)
+                    .with_help("use a positive step to count up or a negative one to count down"),
+            );
+        }
+    }
+    
+    /// Литерал с суффиксом должен помещаться в тип своего суффикса
+    fn check_suffix_range(&mut self, literal: &Literal, negative: bool, expr: &Expr) {
+        let (suffix, text) = match literal {
+            Literal::Int { suffix: Some(suffix), text, .. } | Literal::Float { suffix: Some(suffix), text, .. } => {
+                (suffix, text)
+            }
+            _ => return,
+        };
+        if let Some(ty) = NumericType::from_rust(suffix) {
+            self.check_literal_fits(literal, text, negative, &ty, expr);
+        }
+    }
+
+    /// Числовой литерал, присваиваемый значению типа `target`, должен ему соответствовать
+    fn check_target(&mut self, value: &Expr, target: &DataType) {
+        if let DataType::Optional(inner) = target {
+            if !matches!(value.kind, ExprKind::Nil) {
+                self.check_target(value, inner);
+            }
+            return;
+        }
+        if matches!(value.kind, ExprKind::Nil) {
+            let diag = match target {
+                DataType::Auto => Diagnostic::error("K0213", "cannot infer the type of `nil`", value.span)
+                    .with_help("declare an optional type, for example `int? x = nil`"),
+                _ => Diagnostic::error("K0213", format!("mismatched types: expected `{}`, found `nil`", target), value.span)
+                    .with_help(format!("make the type optional: `{}?`", target)),
+            };
+            self.diagnostics.push(diag);
+            return;
+        }
+        if let (ExprKind::List(items), DataType::List(item_type)) = (&value.kind, target) {
+            for item in items {
+                self.check_target(item, item_type);
+            }
+            return;
+        }
+        if let (ExprKind::Map(entries), DataType::Map(key_type, value_type)) = (&value.kind, target) {
+            for (key, value) in entries {
+                self.check_target(key, key_type);
+                self.check_target(value, value_type);
+            }
+            return;
+        }
+        let Some((literal, negative)) = numeric_literal(value) else {
+            return;
+        };
+        let Some(ty) = NumericType::from_data_type(target) else {
+            return;
+        };
+        let (text, suffix) = match literal {
+            Literal::Int { text, suffix, .. } | Literal::Float { text, suffix, .. } => (text, suffix),
+            Literal::Str(_) => return,
+        };
+
+        if let Some(suffix) = suffix {
+            if suffix != ty.name {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "K0202",
+                        format!("mismatched types: expected `{}`, found `{}` literal `{}{}`", target, suffix, sign(negative), text),
+                        value.span,
+                    )
+                    .with_note(format!("`{}` is `{}` in Rust", target, ty.name)),
+                );
+            }
+            return;
+        }
+
+        if matches!(literal, Literal::Float { .. }) && !ty.is_float {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "K0203",
+                    format!("mismatched types: expected `{}`, found float literal `{}{}`", target, sign(negative), text),
+                    value.span,
+                )
+                .with_help("use `float` or `double` for fractional values"),
+            );
+            return;
+        }
+        self.check_literal_fits(literal, text, negative, &ty, value);
+    }
+
+    /// `negative` — литерал стоит под унарным минусом: у знаковых типов
+    /// отрицательная граница на единицу больше, беззнаковые его не вмещают
+    fn check_literal_fits(&mut self, literal: &Literal, text: &str, negative: bool, ty: &NumericType, expr: &Expr) {
+        let signed = !ty.is_float && !ty.name.starts_with('u');
+        let fits = match literal {
+            Literal::Int { value, .. } if ty.is_float => ty.fits_float(*value as f64),
+            Literal::Int { value, .. } if negative => signed && (*value == 0 || *value - 1 <= ty.max),
+            Literal::Int { value, .. } => *value <= ty.max,
+            Literal::Float { value, .. } if ty.is_float => ty.fits_float(*value),
+            Literal::Float { .. } | Literal::Str(_) => true,
+        };
+        if fits {
+            return;
+        }
+        let range = if ty.is_float {
+            format!("`{}` holds values up to {:e}", ty.name, if ty.name == "f32" { f32::MAX as f64 } else { f64::MAX })
+        } else {
+            let min = if signed { format!("-{}", ty.max + 1) } else { "0".to_string() };
+            format!("`{}` holds values from {} to {}", ty.name, min, ty.max)
+        };
+        let mut diag = Diagnostic::error(
+            "K0201",
+            format!("literal `{}{}` out of range for `{}`", sign(negative), text, ty.name),
+            expr.span,
+        )
+            .with_note(range);
+        if let Literal::Int { suffix, .. } = literal {
+            diag = match suffix {
+                _ if negative && !signed => diag.with_help("use a signed type, such as `i32` or `i64`"),
+                None => diag.with_help(format!("use `auto` with a wider suffix: `{}{}i64`", sign(negative), text)),
+                Some(_) => diag.with_help("use a wider suffix, such as `i64` or `u64`"),
+            };
+        }
+        self.diagnostics.push(diag);
+    }
+}
+
+/// Числовой литерал, возможно под унарным минусом: `5`, `-5`
+fn numeric_literal(expr: &Expr) -> Option<(&Literal, bool)> {
+    match &expr.kind {
+        ExprKind::Literal(literal) => Some((literal, false)),
+        ExprKind::Unary { op: UnaryOp::Neg, operand } => match &operand.kind {
+            ExprKind::Literal(literal) => Some((literal, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Тип константы Rust для переменной верхнего уровня: числовой литерал
+/// своего типа или `true`/`false`; остальные значения константой не станут
+pub(super) fn constant_type(var_type: &DataType, value: Option<&Expr>) -> Option<String> {
+    let value = value?;
+    if let ExprKind::Identifier(name) = &value.kind {
+        let is_bool = name == "true" || name == "false";
+        return (is_bool && matches!(var_type, DataType::Bool | DataType::Auto)).then(|| "bool".to_string());
+    }
+    let (literal, _) = numeric_literal(value)?;
+    let (suffix, default) = match (literal, var_type) {
+        (Literal::Int { suffix, .. }, DataType::Int | DataType::Auto) => (suffix, "i32"),
+        (Literal::Float { suffix, .. }, DataType::Float | DataType::Double | DataType::Auto) => (suffix, "f64"),
+        _ => return None,
+    };
+    match var_type {
+        DataType::Auto => Some(suffix.clone().unwrap_or_else(|| default.to_string())),
+        _ => Some(var_type.to_rust()),
+    }
+}
+
+/// Ближайшее по написанию имя. Допустимое число правок растёт с длиной имени
+/// (треть, но не меньше одной); имя, которое пришлось бы переписать целиком, не подходит.
+pub(super) fn closest<'n>(name: &str, candidates: impl Iterator<Item = &'n str>) -> Option<&'n str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= limit && *distance < candidate.chars().count())
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// `a`, `a and b`, `a, b and c`; длинные списки сокращаются
+fn list_names(names: &[String]) -> String {
+    const SHOWN: usize = 3;
+    match names {
+        [] => String::new(),
+        [only] => only.clone(),
+        _ if names.len() > SHOWN + 1 => {
+            format!("{} and {} more", names[..SHOWN].join(", "), names.len() - SHOWN)
+        }
+        [init @ .., last] => format!("{} and {}", init.join(", "), last),
+    }
+}
+
+fn sign(negative: bool) -> &'static str {
+    if negative { "-" } else { "" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::codegen::lexer::Lexer;
+    use crate::modules::codegen::parser::Parser;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        let tokens = Lexer::new(source).tokenize();
+        let parsed = Parser::new(tokens).parse();
+        assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+        check(&parsed.items)
+    }
+
+    /// Коды диагностик и строки, на которые они указывают
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(&'static str, usize)> {
+        diagnostics.iter().map(|d| (d.code, d.span.line)).collect()
+    }
+
+    #[test]
+    fn literal_inside_interpolation() {
+        let diagnostics = check_source("func f() -> txt\n    return \"big {300u8} and {-1u32}\"\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0201", 2), ("K0201", 2)]);
+        assert_eq!(diagnostics[0].span.column, 18);
+    }
+
+    #[test]
+    fn literal_out_of_range_for_declared_type() {
+        let diagnostics = check_source("func f()\n    int x = 3000000000\n    int y = 2147483647\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0201", 2)]);
+        assert_eq!(diagnostics[0].message, "literal `3000000000` out of range for `i32`");
+        assert_eq!(diagnostics[0].span.column, 13);
+        assert_eq!(diagnostics[0].notes, ["`i32` holds values from -2147483648 to 2147483647"]);
+    }
+
+    #[test]
+    fn literal_out_of_range_for_suffix() {
+        let diagnostics = check_source("func f()\n    auto x = 256u8\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0201", 2)]);
+    }
+
+    #[test]
+    fn suffix_and_float_mismatches() {
+        let diagnostics = check_source("func f()\n    int x = 5i64\n    int y = 1.5\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0202", 2), ("K0203", 3)]);
+        assert_eq!(diagnostics[0].message, "mismatched types: expected `int`, found `i64` literal `5i64`");
+    }
+
+    #[test]
+    fn assigned_literals_fit_the_variable_type() {
+        let diagnostics = check_source(
+            "func f(n: int)\n    n = 2147483647\n    n = 3000000000\n    n += 3000000000\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0201", 3), ("K0201", 4)]);
+        assert_eq!(diagnostics[0].span.column, 9);
+        assert_eq!(diagnostics[1].span.column, 10);
+    }
+
+    #[test]
+    fn negative_literal_ranges() {
+        let diagnostics = check_source(
+            "func f()\n    int min = -2147483648\n    int low = -2147483649\n    auto u = -1u8\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0201", 3), ("K0201", 4)]);
+        assert_eq!(diagnostics[0].message, "literal `-2147483649` out of range for `i32`");
+        assert_eq!(diagnostics[0].span.column, 15);
+        assert_eq!(diagnostics[1].help.as_deref(), Some("use a signed type, such as `i32` or `i64`"));
+    }
+
+    #[test]
+    fn construct_of_unknown_model_and_field() {
+        let diagnostics = check_source(
+            "model User\n    public txt name\n    int age = 3\nend\nfunc g()\n    auto u = Usr { name: \"a\" }\n    \
+             auto v = User { nme: \"a\" }\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0204", 6), ("K0205", 7)]);
+        assert_eq!(diagnostics[0].span.column, 14);
+        assert_eq!(diagnostics[0].help.as_deref(), Some("a model with a similar name exists: `User`"));
+        assert_eq!(diagnostics[1].message, "model `User` has no field named `nme`");
+        assert_eq!(diagnostics[1].notes, ["available fields are: `name`, `age`"]);
+    }
+
+    const STATUS: &str = "enum Status\n    Active\n    Banned(reason: txt, days: int)\nend\n";
+
+    #[test]
+    fn non_exhaustive_enum_match() {
+        let diagnostics = check_source(&format!(
+            "{}func f(s: Status) -> int\n    match s\n        case Status.Active => 1\n    end\n    return 0\nend\n",
+            STATUS
+        ));
+        assert_eq!(codes(&diagnostics), [("K0206", 6)]);
+        assert_eq!(diagnostics[0].span.column, 11);
+        assert_eq!(diagnostics[0].message, "non-exhaustive match: `Status.Banned(..)` not covered");
+    }
+
+    #[test]
+    fn non_exhaustive_bool_match() {
+        let diagnostics = check_source("func f(b: bool) -> int\n    match b\n        case true => 1\n    end\n    return 0\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0206", 2)]);
+        assert_eq!(diagnostics[0].message, "non-exhaustive match: `false` not covered");
+    }
+
+    #[test]
+    fn unknown_variants_and_field_counts() {
+        let diagnostics = check_source(&format!(
+            "{}func f()\n    auto x = Status.Banned(\"a\")\n    auto y = Status.Bannd\nend\n",
+            STATUS
+        ));
+        assert_eq!(codes(&diagnostics), [("K0208", 6), ("K0207", 7)]);
+        assert_eq!(diagnostics[0].message, "variant `Status.Banned` has 2 fields, but 1 was given");
+        assert_eq!(diagnostics[0].help.as_deref(), Some("pass the fields in order: `Status.Banned(reason, days)`"));
+        assert_eq!(diagnostics[1].span.column, 14);
+        assert_eq!(diagnostics[1].help.as_deref(), Some("a variant with a similar name exists: `Banned`"));
+    }
+
+    #[test]
+    fn mismatched_patterns() {
+        let diagnostics = check_source(&format!(
+            "{}func f(s: Status) -> int\n    match s\n        case Active => 1\n        case Status.Banned(r) => 2\n        \
+             case 3 => 4\n    end\n    return 0\nend\n",
+            STATUS
+        ));
+        assert_eq!(codes(&diagnostics), [("K0208", 8), ("K0209", 9)]);
+        assert_eq!(diagnostics[0].message, "pattern binds 1 field of `Status.Banned`, which has 2");
+        assert_eq!(diagnostics[1].span.column, 14);
+    }
+
+    #[test]
+    fn zero_for_step() {
+        let diagnostics = check_source("func f()\n    for i = 0, 10, 0\n    end\n    for j = 0, 10, -1\n    end\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0210", 2)]);
+        assert_eq!(diagnostics[0].span.column, 20);
+    }
+
+    #[test]
+    fn raise_of_undeclared_error_code() {
+        let diagnostics = check_source("error_code NotFound = 404\nfunc f()\n    raise NotFond\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0211", 3)]);
+        assert_eq!(diagnostics[0].span.column, 5);
+    }
+
+    #[test]
+    fn catch_of_undeclared_error_code() {
+        let diagnostics = check_source(
+            "error_code NotFound = 404\nfunc f()\n    try\n        f()\n    catch NotFond\n        f()\n    end\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0211", 5)]);
+        assert_eq!(diagnostics[0].help.as_deref(), Some("an error code with a similar name exists: `NotFound`"));
+    }
+
+    #[test]
+    fn unreachable_catch_clauses() {
+        let diagnostics = check_source(
+            "error_code NotFound = 404\nfunc f()\n    try\n        f()\n    catch NotFound\n        f()\n    \
+             catch NotFound e\n        f()\n    catch err\n        f()\n    catch NotFound\n        f()\n    end\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0212", 7), ("K0212", 11)]);
+        assert_eq!(diagnostics[0].span.column, 5);
+        assert_eq!(diagnostics[0].notes, ["errors reaching it are already caught on line 5"]);
+        assert_eq!(diagnostics[1].notes, ["errors reaching it are already caught on line 9"]);
+    }
+
+    #[test]
+    fn nil_needs_an_optional_type() {
+        let diagnostics = check_source("func f()\n    int x = nil\n    auto y = nil\n    int? z = nil\nend\n");
+        assert_eq!(codes(&diagnostics), [("K0213", 2), ("K0213", 3)]);
+        assert_eq!(diagnostics[0].span.column, 13);
+        assert_eq!(diagnostics[0].help.as_deref(), Some("make the type optional: `int?`"));
+        assert_eq!(diagnostics[1].message, "cannot infer the type of `nil`");
+    }
+
+    #[test]
+    fn fields_without_default_value() {
+        let diagnostics = check_source(&format!(
+            "{}model Address\n    txt city\nend\nmodel User\n    txt name\n    Status status\n    Address home\n    \
+             Status last = Status.Active\nend\nfunc f()\n    auto u = User {{ name: \"a\" }}\n    \
+             auto v = User {{ name: \"b\", status: Status.Active }}\nend\n",
+            STATUS
+        ));
+        assert_eq!(codes(&diagnostics), [("K0216", 10), ("K0217", 15)]);
+        assert_eq!(diagnostics[0].message, "field `status` of type `Status` needs a default value");
+        assert_eq!(diagnostics[0].help.as_deref(), Some("write one, such as `Status status = Status.Active`"));
+        assert_eq!(diagnostics[1].message, "missing field `status` in `User`");
+        assert_eq!(diagnostics[1].span.column, 14);
+    }
+
+    #[test]
+    fn doc_comment_on_dropped_top_level_variable() {
+        let diagnostics = check_source(
+            "/// Limit\npublic int LIMIT = 5\n/// Ratio\nauto RATIO = -0.5\n/// Name\ntxt NAME = \"a\"\nint plain = len([1])\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0218", 6)]);
+        assert!(!diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].message, "doc comment on top-level variable `NAME` is ignored");
+    }
+
+    #[test]
+    fn coalesce_needs_an_optional_left_side() {
+        let diagnostics = check_source(
+            "func find(id: int) -> int\n    return id\nend\nfunc maybe(id: int) -> int?\n    return nil\nend\n\
+             func f(id: int) -> int\n    int? x = maybe(id)\n    return (find(id) ?? 0) + (id ?? 1) + (x ?? maybe(id) ?? 2)\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0219", 9), ("K0219", 9)]);
+        assert_eq!(diagnostics[0].message, "left side of `??` has non-optional type `int`");
+        assert_eq!(diagnostics[1].span.column, 31);
+    }
+
+    #[test]
+    fn duplicate_models_and_fields() {
+        let diagnostics = check_source(
+            "model User\n    txt name\n    int age\n    txt name\nend\nenum Role\n    Guest\nend\nmodel User\n    txt id\nend\n",
+        );
+        assert_eq!(codes(&diagnostics), [("K0220", 9), ("K0220", 4)]);
+        assert_eq!(diagnostics[0].message, "the type `User` is defined multiple times");
+        assert_eq!(diagnostics[0].labels[0].span.line, 1);
+        assert_eq!(diagnostics[1].message, "the field `name` is defined multiple times");
+    }
+
+    #[test]
+    fn duplicate_variants_and_match_arms() {
+        let diagnostics = check_source(&format!(
+            "enum Role\n    Guest\n    Guest\nend\n{}func f(s: Status) -> int\n    match s\n        case Active\n            \
+             return 1\n        case Status.Active\n            return 2\n        case _\n            return 3\n        \
+             case Banned(_)\n            return 4\n    end\nend\n",
+            STATUS
+        ));
+        assert_eq!(codes(&diagnostics), [("K0220", 3), ("K0221", 13), ("K0221", 17)]);
+        assert_eq!(diagnostics[1].notes[0], "`Status.Active` is already matched on line 11");
+    }
+}
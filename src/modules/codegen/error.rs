@@ -0,0 +1,295 @@
+// Ошибки трансляции Krait
+//
+// Каждая ошибка имеет стабильный код (`K0001`, ...), по которому CI и
+// инструменты могут различать виды ошибок, не разбирая текст сообщения.
+
+use super::lexer::TokenType;
+use super::span::Span;
+use crate::modules::diagnostics::Diagnostic;
+use thiserror::Error;
+
+/// Ошибка синтаксического анализа. Позиция хранится в `span` и в текст
+/// сообщения не входит — её выводит `Diagnostic`.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ParseError {
+    #[error("expected {}, found {found}", describe_expected(expected))]
+    UnexpectedToken { span: Span, expected: Vec<TokenType>, found: TokenType },
+
+    #[error("expected {what}, found {found}")]
+    ExpectedName { span: Span, what: &'static str, found: TokenType },
+
+    #[error("expected data type, found {found}")]
+    ExpectedType { span: Span, found: TokenType },
+
+    #[error("expected expression, found {found}")]
+    ExpectedExpression { span: Span, found: TokenType },
+
+    #[error("expected route path string, found {found}")]
+    ExpectedRoutePath { span: Span, found: TokenType },
+
+    #[error("expected HTTP method, found {found}")]
+    ExpectedHttpMethod { span: Span, found: TokenType },
+
+    /// Слева от оператора присваивания (`found`) стоит не переменная
+    #[error("invalid left-hand side of {found}")]
+    InvalidAssignTarget { span: Span, found: TokenType },
+
+    #[error("comparison operators cannot be chained")]
+    ChainedComparison { span: Span, found: TokenType },
+
+    #[error("expected pattern, found {found}")]
+    ExpectedPattern { span: Span, found: TokenType },
+
+    /// `break` или `continue` (`found`) вне цикла
+    #[error("{found} outside of a loop")]
+    OutsideLoop { span: Span, found: TokenType },
+
+    #[error("use of undeclared label `{label}`")]
+    UndeclaredLabel { span: Span, label: String, found: TokenType },
+
+    /// Статус `error_code` (`found`) — не код ошибки HTTP (400–599)
+    #[error("{found} is not an HTTP error status")]
+    InvalidStatus { span: Span, found: TokenType },
+}
+
+impl ParseError {
+    /// Стабильный код ошибки
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "K0001",
+            ParseError::ExpectedName { .. } => "K0002",
+            ParseError::ExpectedType { .. } => "K0003",
+            ParseError::ExpectedExpression { .. } => "K0004",
+            ParseError::ExpectedRoutePath { .. } => "K0005",
+            ParseError::ExpectedHttpMethod { .. } => "K0006",
+            ParseError::InvalidAssignTarget { .. } => "K0007",
+            ParseError::ChainedComparison { .. } => "K0008",
+            ParseError::ExpectedPattern { .. } => "K0009",
+            ParseError::OutsideLoop { .. } => "K0010",
+            ParseError::UndeclaredLabel { .. } => "K0011",
+            ParseError::InvalidStatus { .. } => "K0012",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::ExpectedName { span, .. }
+            | ParseError::ExpectedType { span, .. }
+            | ParseError::ExpectedExpression { span, .. }
+            | ParseError::ExpectedRoutePath { span, .. }
+            | ParseError::ExpectedHttpMethod { span, .. }
+            | ParseError::InvalidAssignTarget { span, .. }
+            | ParseError::ChainedComparison { span, .. }
+            | ParseError::ExpectedPattern { span, .. }
+            | ParseError::OutsideLoop { span, .. }
+            | ParseError::UndeclaredLabel { span, .. }
+            | ParseError::InvalidStatus { span, .. } => *span,
+        }
+    }
+
+    /// Токен, на котором остановился парсер
+    pub fn found(&self) -> &TokenType {
+        match self {
+            ParseError::UnexpectedToken { found, .. }
+            | ParseError::ExpectedName { found, .. }
+            | ParseError::ExpectedType { found, .. }
+            | ParseError::ExpectedExpression { found, .. }
+            | ParseError::ExpectedRoutePath { found, .. }
+            | ParseError::ExpectedHttpMethod { found, .. }
+            | ParseError::InvalidAssignTarget { found, .. }
+            | ParseError::ChainedComparison { found, .. }
+            | ParseError::ExpectedPattern { found, .. }
+            | ParseError::OutsideLoop { found, .. }
+            | ParseError::UndeclaredLabel { found, .. }
+            | ParseError::InvalidStatus { found, .. } => found,
+        }
+    }
+
+    /// Виды токенов, которые парсер был готов принять
+    pub fn expected(&self) -> Vec<TokenType> {
+        match self {
+            ParseError::UnexpectedToken { expected, .. } => expected.clone(),
+            ParseError::ExpectedName { .. } => vec![TokenType::Identifier(String::new())],
+            ParseError::ExpectedType { .. } => vec![
+                TokenType::Int,
+                TokenType::Float,
+                TokenType::Double,
+                TokenType::Txt,
+                TokenType::Bool,
+                TokenType::Auto,
+                TokenType::Json,
+                TokenType::Identifier("list".to_string()),
+                TokenType::Identifier("map".to_string()),
+                // Модель, перечисление или псевдоним
+                TokenType::Identifier(String::new()),
+            ],
+            ParseError::ExpectedExpression { .. } => vec![
+                TokenType::Number(String::new()),
+                TokenType::String(String::new()),
+                TokenType::Identifier(String::new()),
+                TokenType::LeftParen,
+            ],
+            ParseError::ExpectedRoutePath { .. } => vec![TokenType::String(String::new())],
+            ParseError::ExpectedHttpMethod { .. } => vec![
+                TokenType::Get,
+                TokenType::Post,
+                TokenType::Put,
+                TokenType::Delete,
+            ],
+            ParseError::InvalidAssignTarget { .. } => vec![TokenType::Identifier(String::new())],
+            ParseError::ChainedComparison { .. } => vec![TokenType::And, TokenType::Or],
+            ParseError::ExpectedPattern { .. } => vec![
+                TokenType::Number(String::new()),
+                TokenType::String(String::new()),
+                TokenType::Identifier(String::new()),
+            ],
+            ParseError::OutsideLoop { .. } => vec![],
+            ParseError::UndeclaredLabel { .. } => vec![TokenType::Identifier(String::new())],
+            ParseError::InvalidStatus { .. } => vec![TokenType::Number(String::new())],
+        }
+    }
+}
+
+/// Ошибка лексического анализа. Лексер не останавливается на ней:
+/// вместо плохого фрагмента он выдаёт `TokenType::Error` и продолжает.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LexError {
+    #[error("unexpected character `{ch}`")]
+    UnexpectedChar { span: Span, ch: char },
+
+    #[error("unterminated string literal")]
+    UnterminatedString { span: Span },
+
+    #[error("unknown escape sequence `\\{ch}`")]
+    InvalidEscape { span: Span, ch: char },
+
+    #[error("malformed number literal `{text}`")]
+    MalformedNumber { span: Span, text: String },
+
+    #[error("unterminated block comment")]
+    UnterminatedComment { span: Span },
+
+    #[error("unterminated `{{` in string interpolation")]
+    UnterminatedInterpolation { span: Span },
+
+    #[error("empty interpolation `{{}}` in string")]
+    EmptyInterpolation { span: Span },
+}
+
+impl LexError {
+    /// Стабильный код ошибки
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnexpectedChar { .. } => "K0101",
+            LexError::UnterminatedString { .. } => "K0102",
+            LexError::InvalidEscape { .. } => "K0103",
+            LexError::MalformedNumber { .. } => "K0104",
+            LexError::UnterminatedComment { .. } => "K0105",
+            LexError::UnterminatedInterpolation { .. } => "K0106",
+            LexError::EmptyInterpolation { .. } => "K0107",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedChar { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedComment { span }
+            | LexError::UnterminatedInterpolation { span }
+            | LexError::EmptyInterpolation { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::MalformedNumber { span, .. } => *span,
+        }
+    }
+}
+
+fn describe_expected(expected: &[TokenType]) -> String {
+    match expected {
+        [] => "nothing".to_string(),
+        [single] => single.to_string(),
+        [init @ .., last] => format!(
+            "one of {} or {}",
+            init.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", "),
+            last
+        ),
+    }
+}
+
+/// Ошибка трансляции Krait → Rust
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TranslateError {
+    /// Ошибки лексера, парсера и семантических проверок, найденные за один проход
+    #[error("{}", join_lines(.0))]
+    Diagnostics(Vec<Diagnostic>),
+}
+
+impl TranslateError {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            TranslateError::Diagnostics(diagnostics) => diagnostics,
+        }
+    }
+}
+
+fn join_lines(diagnostics: &[Diagnostic]) -> String {
+    diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize, column: usize) -> Span {
+        Span { line, column, ..Span::default() }
+    }
+
+    #[test]
+    fn unexpected_token_lists_expected_kinds() {
+        let err = ParseError::UnexpectedToken {
+            span: span(3, 7),
+            expected: vec![TokenType::Comma, TokenType::RightParen],
+            found: TokenType::Identifier("x".to_string()),
+        };
+        assert_eq!(err.code(), "K0001");
+        assert_eq!(err.span(), span(3, 7));
+        assert_eq!(err.to_string(), "expected one of `,` or `)`, found identifier `x`");
+        assert_eq!(err.expected(), [TokenType::Comma, TokenType::RightParen]);
+    }
+
+    #[test]
+    fn codes_are_stable() {
+        let found = TokenType::Eof;
+        let errors = [
+            ParseError::ExpectedName { span: span(1, 1), what: "function name", found: found.clone() },
+            ParseError::ExpectedType { span: span(1, 1), found: found.clone() },
+            ParseError::ExpectedExpression { span: span(1, 1), found: found.clone() },
+            ParseError::ExpectedRoutePath { span: span(1, 1), found: found.clone() },
+            ParseError::ExpectedHttpMethod { span: span(1, 1), found },
+        ];
+        let codes: Vec<_> = errors.iter().map(ParseError::code).collect();
+        assert_eq!(codes, ["K0002", "K0003", "K0004", "K0005", "K0006"]);
+        assert_eq!(errors[0].to_string(), "expected function name, found end of file");
+    }
+
+    #[test]
+    fn translate_error_exposes_diagnostics() {
+        let err = crate::translate("func (").unwrap_err();
+        let TranslateError::Diagnostics(diagnostics) = &err;
+        assert_eq!(err.diagnostics(), diagnostics.as_slice());
+        assert_eq!(diagnostics[0].code, "K0002");
+        assert_eq!((diagnostics[0].span.line, diagnostics[0].span.column), (1, 6));
+    }
+
+    #[test]
+    fn expected_type_lists_type_names() {
+        let err = ParseError::ExpectedType { span: span(2, 9), found: TokenType::Comma };
+        assert_eq!(err.code(), "K0003");
+        let expected = err.expected();
+        assert!(expected.contains(&TokenType::Int));
+        assert!(expected.contains(&TokenType::Json));
+        assert!(expected.contains(&TokenType::Identifier("list".to_string())));
+        assert!(expected.contains(&TokenType::Identifier("map".to_string())));
+        assert!(expected.contains(&TokenType::Identifier(String::new())));
+    }
+}
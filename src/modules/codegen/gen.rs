@@ -1,13 +1,67 @@
 // Генератор Rust кода из AST Krait
-use super::parser::{Expr, FunctionDef, RouteDef, Statement, TopLevel, DataType};
+use super::parser::{
+    ArmBody, Assoc, BinOp, CatchClause, DataType, EnumDef, ErrorCodeDef, Expr, ExprKind, ForIter, FunctionDef,
+    InterpolationPart, Literal, MatchArm, ModelDef, Pattern, RouteDef, Statement, StatementKind, TopLevel, UnaryOp,
+    ATOM_PRECEDENCE, UNARY_PRECEDENCE,
+};
+use super::check::constant_type;
 use super::libs::LibraryRegistry;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct CodeGenerator {
     output: String,
     indent_level: usize,
     required_libs: HashSet<String>,
     library_registry: LibraryRegistry,
+    /// Переменные текущей функции, которым что-то присваивается: они объявляются `let mut`
+    assigned: HashSet<String>,
+    /// Типы Rust параметров и переменных текущей функции (для выбора `pow`/`powf`)
+    local_types: HashMap<String, String>,
+    /// Типы Rust констант верхнего уровня
+    constants: HashMap<String, String>,
+    /// Возвращаемые типы функций программы
+    function_types: HashMap<String, DataType>,
+    /// Типы параметров функций программы
+    function_params: HashMap<String, Vec<DataType>>,
+    /// Возвращаемый тип текущей функции; `None` внутри обработчика маршрута
+    return_type: Option<DataType>,
+    /// Импортированные модули программы: имя в Krait → модуль Rust
+    modules: HashMap<String, String>,
+    /// Модули проекта: соседние файлы `.kr`, которые транслируются вместе с этим
+    project_modules: HashSet<String>,
+    /// Модели программы по имени
+    models: HashMap<String, ModelDef>,
+    /// Перечисления программы по имени
+    enums: HashMap<String, EnumDef>,
+    /// Объявленные коды `error_code`
+    error_codes: Vec<String>,
+    /// Функции, которые могут завершиться `raise` (в том числе через вызовы):
+    /// они возвращают `Result<_, KraitError>`
+    raising: HashSet<String>,
+    /// Текущая функция или обработчик возвращает `Result<_, KraitError>`
+    raises: bool,
+    /// Объемлющие `try` текущего оператора, от внешнего к внутреннему
+    handlers: Vec<Handler>,
+    /// Метки Rust объемлющих циклов; `None` — цикл без метки
+    loops: Vec<Option<String>>,
+}
+
+/// Куда уходит управление из тела `try`
+#[derive(Clone)]
+enum Handler {
+    /// Тело `try`: ошибка прерывает блок `'try_N: { ... }` и попадает в ветки `catch`
+    Body { label: String, loop_depth: usize },
+    /// Тело и ветки `catch` блока с `finally`: перед выходом из них выполняется `finally`
+    Finally { body: Vec<Statement>, loop_depth: usize },
+}
+
+impl Handler {
+    /// Сколько циклов было открыто вокруг `try`
+    fn loop_depth(&self) -> usize {
+        match self {
+            Handler::Body { loop_depth, .. } | Handler::Finally { loop_depth, .. } => *loop_depth,
+        }
+    }
 }
 
 impl CodeGenerator {
@@ -17,9 +71,31 @@ impl CodeGenerator {
             indent_level: 0,
             required_libs: HashSet::new(),
             library_registry: LibraryRegistry::new(),
+            assigned: HashSet::new(),
+            local_types: HashMap::new(),
+            constants: HashMap::new(),
+            function_types: HashMap::new(),
+            function_params: HashMap::new(),
+            return_type: None,
+            modules: HashMap::new(),
+            project_modules: HashSet::new(),
+            models: HashMap::new(),
+            enums: HashMap::new(),
+            error_codes: Vec::new(),
+            raising: HashSet::new(),
+            raises: false,
+            handlers: Vec::new(),
+            loops: Vec::new(),
         }
     }
     
+    /// Модули проекта, которые можно импортировать: `import utils from utils`
+    /// становится `mod utils;` только для них
+    pub fn with_project_modules(mut self, modules: impl IntoIterator<Item = String>) -> Self {
+        self.project_modules.extend(modules);
+        self
+    }
+    
     fn indent(&self) -> String {
         "    ".repeat(self.indent_level)
     }
@@ -30,36 +106,100 @@ impl CodeGenerator {
         self.output.push('\n');
     }
     
-    fn write(&mut self, content: &str) {
-        self.output.push_str(content);
+    /// Документирующие комментарии Krait → Rust `///`
+    fn write_doc(&mut self, doc: &[String]) {
+        for line in doc {
+            if line.is_empty() {
+                self.write_line("///");
+            } else {
+                self.write_line(&format!("/// {}", line));
+            }
+        }
     }
     
     pub fn generate(&mut self, items: &[TopLevel]) -> String {
         let mut functions = Vec::new();
         let mut routes = Vec::new();
+        let mut models = Vec::new();
+        let mut enums = Vec::new();
+        let mut error_codes = Vec::new();
+        let mut constants = Vec::new();
         let mut has_main = false;
         
         for item in items {
             match item {
-                TopLevel::Import { module: _, from } => {
+                TopLevel::Import { module, from, .. } => {
+                    // Соседний файл проекта `from.kr`; незнакомые библиотеки, как и раньше, пропускаются
+                    if self.library_registry.get(from).is_none() && self.project_modules.contains(from) {
+                        self.modules.insert(module.clone(), from.clone());
+                    }
                     self.required_libs.insert(from.clone());
                 }
                 TopLevel::Function(func) => {
                     if func.name == "main" {
                         has_main = true;
                     }
+                    self.function_types.insert(func.name.clone(), func.return_type.clone());
+                    self.function_params
+                        .insert(func.name.clone(), func.params.iter().map(|(_, ty)| ty.clone()).collect());
                     functions.push(func);
                 }
                 TopLevel::Route(_route) => {
                     self.required_libs.insert("rest".to_string());
                     routes.push(_route);
                 }
-                TopLevel::Statement(_stmt) => {}
+                TopLevel::Model(model) => {
+                    self.required_libs.insert("serde".to_string());
+                    self.models.insert(model.name.clone(), model.clone());
+                    models.push(model);
+                }
+                TopLevel::Enum(enum_def) => {
+                    self.required_libs.insert("serde".to_string());
+                    self.enums.insert(enum_def.name.clone(), enum_def.clone());
+                    enums.push(enum_def);
+                }
+                TopLevel::ErrorCode(error_code) => {
+                    self.error_codes.push(error_code.name.clone());
+                    error_codes.push(error_code);
+                }
+                // Переменные верхнего уровня с литеральным значением становятся константами,
+                // остальные не генерируются
+                TopLevel::Statement(stmt) => {
+                    if let StatementKind::VarDecl { name, var_type, value, .. } = &stmt.kind {
+                        if let Some(ty) = constant_type(var_type, value.as_ref()) {
+                            self.constants.insert(name.clone(), ty);
+                            constants.push(stmt);
+                        }
+                    }
+                }
+                // Псевдонимы уже раскрыты проходом `resolve`
+                TopLevel::TypeAlias(_) | TopLevel::Error(_) => {}
             }
         }
         
-        self.generate_imports();
-        self.output.push('\n');
+        self.raising = raising_functions(&functions, &self.error_codes);
+        
+        if !error_codes.is_empty() {
+            self.generate_error_codes(&error_codes, !routes.is_empty());
+            self.output.push('\n');
+        }
+        
+        for enum_def in &enums {
+            self.generate_enum(enum_def);
+            self.output.push('\n');
+        }
+        
+        for model in &models {
+            self.generate_model(model);
+            self.output.push('\n');
+        }
+        
+        if !constants.is_empty() {
+            for stmt in &constants {
+                self.generate_constant(stmt);
+            }
+            self.output.push('\n');
+        }
         
         for func in &functions {
             if func.name != "main" {
@@ -90,6 +230,20 @@ impl CodeGenerator {
             self.write_line("}");
         }
         
+        // Импорты известны только после генерации тел: их добавляют `map`, `json` и т.п.
+        let body = std::mem::take(&mut self.output);
+        self.generate_imports();
+        self.output.push('\n');
+        
+        let bodies = functions.iter().map(|f| &f.body).chain(routes.iter().map(|r| &r.body));
+        if bodies.into_iter().any(|body| any_expr(body, &|e| matches!(e.kind, ExprKind::Index { .. }))) {
+            self.write_line("// Индексация списков `xs[i]` проверяет границы: индекс вне `0..len(xs)`,");
+            self.write_line("// в том числе отрицательный, завершает программу паникой. Так же ведёт себя");
+            self.write_line("// поиск `m[k]` ключа, которого нет в словаре.");
+            self.output.push('\n');
+        }
+        
+        self.output.push_str(&body);
         self.output.clone()
     }
     
@@ -97,35 +251,250 @@ impl CodeGenerator {
         self.write_line("// Автоматически сгенерировано из Krait");
         self.output.push('\n');
         
-        let libs: Vec<String> = self.required_libs.iter().cloned().collect();
+        let mut libs: Vec<String> = self.required_libs.iter().cloned().collect();
+        libs.sort();
         let imports = self.library_registry.get_imports(&libs);
         
         for import in imports {
             self.write_line(&import);
         }
+        
+        let mut modules: Vec<(String, String)> = self.modules.iter().map(|(a, m)| (a.clone(), m.clone())).collect();
+        modules.sort();
+        for (alias, module) in modules {
+            self.write_line(&format!("mod {};", module));
+            if alias != module {
+                self.write_line(&format!("use {} as {};", module, alias));
+            }
+        }
+    }
+    
+    /// Тип Rust для типа Krait; подключает библиотеки, которые нужны этому типу
+    fn use_type(&mut self, ty: &DataType) -> String {
+        match ty {
+            DataType::List(item) => {
+                self.use_type(item);
+            }
+            DataType::Map(key, value) => {
+                self.required_libs.insert("collections".to_string());
+                self.use_type(key);
+                self.use_type(value);
+            }
+            DataType::Json => {
+                self.required_libs.insert("json".to_string());
+            }
+            _ => {}
+        }
+        ty.to_rust()
+    }
+    
+    /// Модель → структура с serde. Поля без значения по умолчанию получают
+    /// `Default::default()`; `#[serde(default)]` позволяет пропускать поля в JSON.
+    /// Типы без `Default` (перечисления) check пропускает только со значением.
+    fn generate_model(&mut self, model: &ModelDef) {
+        let visibility = if model.is_public { "pub " } else { "" };
+        let has_defaults = model.fields.iter().any(|field| field.default.is_some());
+        self.assigned.clear();
+        self.local_types.clear();
+        
+        self.write_doc(&model.doc);
+        if has_defaults {
+            self.write_line("#[derive(Debug, Clone, Serialize, Deserialize)]");
+        } else {
+            self.write_line("#[derive(Debug, Clone, Default, Serialize, Deserialize)]");
+        }
+        self.write_line("#[serde(default)]");
+        if model.fields.is_empty() {
+            self.write_line(&format!("{}struct {} {{}}", visibility, model.name));
+            return;
+        }
+        self.write_line(&format!("{}struct {} {{", visibility, model.name));
+        self.indent_level += 1;
+        for field in &model.fields {
+            let field_visibility = if field.is_public { "pub " } else { "" };
+            let ty = self.use_type(&field.field_type);
+            self.write_doc(&field.doc);
+            self.write_line(&format!("{}{}: {},", field_visibility, field.name, ty));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+        
+        if !has_defaults {
+            return;
+        }
+        self.output.push('\n');
+        self.write_line(&format!("impl Default for {} {{", model.name));
+        self.indent_level += 1;
+        self.write_line("fn default() -> Self {");
+        self.indent_level += 1;
+        self.write_line(&format!("{} {{", model.name));
+        self.indent_level += 1;
+        for field in &model.fields {
+            let value = match &field.default {
+                Some(default) => self.generate_value(default, &field.field_type),
+                None => "Default::default()".to_string(),
+            };
+            self.write_line(&format!("{}: {},", field.name, value));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+    }
+    
+    /// Коды `error_code` → `enum KraitError` со статусом HTTP и текстом ошибки.
+    /// В программе с маршрутами ошибка из обработчика сразу становится ответом.
+    fn generate_error_codes(&mut self, error_codes: &[&ErrorCodeDef], has_routes: bool) {
+        self.write_line("#[derive(Debug, Clone, Copy, PartialEq, Eq)]");
+        self.write_line("pub enum KraitError {");
+        self.indent_level += 1;
+        for error_code in error_codes {
+            self.write_doc(&error_code.doc);
+            self.write_line(&format!("{},", error_code.name));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.output.push('\n');
+        
+        self.write_line("impl KraitError {");
+        self.indent_level += 1;
+        self.write_line("pub fn code(&self) -> i32 {");
+        self.indent_level += 1;
+        self.write_line("match self {");
+        self.indent_level += 1;
+        for error_code in error_codes {
+            self.write_line(&format!("KraitError::{} => {},", error_code.name, error_code.status));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.output.push('\n');
+        self.write_line("pub fn message(&self) -> &'static str {");
+        self.indent_level += 1;
+        self.write_line("match self {");
+        self.indent_level += 1;
+        for error_code in error_codes {
+            let message = error_code.message.as_deref().unwrap_or(&error_code.name);
+            self.write_line(&format!("KraitError::{} => {:?},", error_code.name, message));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.output.push('\n');
+        
+        self.write_line("impl std::fmt::Display for KraitError {");
+        self.indent_level += 1;
+        self.write_line("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {");
+        self.indent_level += 1;
+        self.write_line("f.write_str(self.message())");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.indent_level -= 1;
+        self.write_line("}");
+        self.output.push('\n');
+        
+        self.write_line("impl std::error::Error for KraitError {}");
+        
+        if has_routes {
+            self.output.push('\n');
+            self.write_line("impl actix_web::ResponseError for KraitError {");
+            self.indent_level += 1;
+            self.write_line("fn status_code(&self) -> actix_web::http::StatusCode {");
+            self.indent_level += 1;
+            self.write_line("actix_web::http::StatusCode::from_u16(self.code() as u16)");
+            self.indent_level += 1;
+            self.write_line(".unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)");
+            self.indent_level -= 2;
+            self.write_line("}");
+            self.indent_level -= 1;
+            self.write_line("}");
+        }
+    }
+    
+    /// Перечисление → `enum` Rust; варианты с полями становятся структурными вариантами
+    fn generate_enum(&mut self, enum_def: &EnumDef) {
+        let visibility = if enum_def.is_public { "pub " } else { "" };
+        self.write_doc(&enum_def.doc);
+        self.write_line("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]");
+        self.write_line(&format!("{}enum {} {{", visibility, enum_def.name));
+        self.indent_level += 1;
+        for variant in &enum_def.variants {
+            self.write_doc(&variant.doc);
+            if variant.fields.is_empty() {
+                self.write_line(&format!("{},", variant.name));
+                continue;
+            }
+            let fields = variant
+                .fields
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", name, self.use_type(ty)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.write_line(&format!("{} {{ {} }},", variant.name, fields));
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+    }
+    
+    /// Переменная верхнего уровня → `const`; её тип уже определил `constant_type`
+    fn generate_constant(&mut self, stmt: &Statement) {
+        let StatementKind::VarDecl { name, value: Some(value), is_public, doc, .. } = &stmt.kind else {
+            return;
+        };
+        let visibility = if *is_public { "pub " } else { "" };
+        let ty = self.constants[name].clone();
+        let value = self.generate_expr(value);
+        self.write_doc(doc);
+        if name.chars().any(|c| c.is_lowercase()) {
+            self.write_line("#[allow(non_upper_case_globals)]");
+        }
+        self.write_line(&format!("{}const {}: {} = {};", visibility, name, ty, value));
+    }
+    
+    /// `mut ` для переменных, которым присваивается значение в текущей функции
+    fn mutability(&self, name: &str) -> &'static str {
+        if self.assigned.contains(name) { "mut " } else { "" }
     }
     
     fn generate_function(&mut self, func: &FunctionDef) {
         let visibility = if func.is_public { "pub " } else { "" };
-        let return_type = func.return_type.to_rust();
-        
-        let params = func.params
+        let return_type = self.use_type(&func.return_type);
+        self.assigned = assigned_variables(&func.body);
+        self.return_type = Some(func.return_type.clone());
+        self.raises = self.raising.contains(&func.name);
+        self.local_types = func.params
             .iter()
-            .map(|(name, ty)| format!("{}: {}", name, ty.to_rust()))
-            .collect::<Vec<_>>()
-            .join(", ");
+            .map(|(name, ty)| (name.clone(), ty.to_rust()))
+            .collect();
         
-        let return_str = if matches!(func.return_type, DataType::Auto) {
-            String::new()
-        } else {
-            format!(" -> {}", return_type)
+        let mut params = Vec::new();
+        for (name, ty) in &func.params {
+            let ty = self.use_type(ty);
+            params.push(format!("{}{}: {}", self.mutability(name), name, ty));
+        }
+        let params = params.join(", ");
+        
+        let is_unit = matches!(func.return_type, DataType::Auto);
+        let return_str = match (self.raises, is_unit) {
+            (true, true) => " -> Result<(), KraitError>".to_string(),
+            (true, false) => format!(" -> Result<{}, KraitError>", return_type),
+            (false, true) => String::new(),
+            (false, false) => format!(" -> {}", return_type),
         };
         
+        self.write_doc(&func.doc);
         self.write_line(&format!("{}fn {}({}){} {{", visibility, func.name, params, return_str));
         self.indent_level += 1;
         
-        for stmt in &func.body {
-            self.generate_statement(stmt);
+        self.generate_body(&func.body);
+        if self.raises && is_unit && !ends_diverging(&func.body) {
+            self.write_line("Ok(())");
         }
         
         self.indent_level -= 1;
@@ -135,17 +504,28 @@ impl CodeGenerator {
     fn generate_route_handler(&mut self, route: &RouteDef) {
         let method = route.method.to_lowercase();
         let path = &route.path;
+        self.assigned = assigned_variables(&route.body);
+        self.return_type = None;
+        self.raises = body_raises(&route.body, &self.raising, &self.error_codes);
+        self.local_types.clear();
         
+        self.write_doc(&route.doc);
         self.write_line(&format!("#[{}(\"{}\")]", method, path));
-        self.write_line("async fn handler() -> HttpResponse {");
+        if self.raises {
+            self.write_line("async fn handler() -> Result<HttpResponse, KraitError> {");
+        } else {
+            self.write_line("async fn handler() -> HttpResponse {");
+        }
         self.indent_level += 1;
         
-        for stmt in &route.body {
-            self.generate_statement(stmt);
-        }
+        self.generate_body(&route.body);
         
-        if !route.body.iter().any(|s| matches!(s, Statement::Return(_))) {
-            self.write_line("HttpResponse::Ok().finish()");
+        if !route.body.iter().any(|s| matches!(s.kind, StatementKind::Return(_))) {
+            if self.raises {
+                self.write_line("Ok(HttpResponse::Ok().finish())");
+            } else {
+                self.write_line("HttpResponse::Ok().finish()");
+            }
         }
         
         self.indent_level -= 1;
@@ -177,37 +557,102 @@ impl CodeGenerator {
         self.write_line("}");
     }
     
+    /// Тело функции или обработчика: `return` последним оператором становится
+    /// хвостовым выражением, остальные — `return ...;`
+    fn generate_body(&mut self, body: &[Statement]) {
+        for (i, stmt) in body.iter().enumerate() {
+            match &stmt.kind {
+                StatementKind::Return(Some(expr)) if i + 1 == body.len() => {
+                    let expr_str = self.generate_return_value(expr);
+                    self.write_line(&expr_str);
+                }
+                _ => self.generate_statement(stmt),
+            }
+        }
+    }
+    
+    /// Возвращаемое значение: ответ HTTP в маршруте, `json!` для `json`;
+    /// в функции, которая может вызвать `raise`, — обёрнутое в `Ok`
+    fn generate_return_value(&mut self, expr: &Expr) -> String {
+        let value = match self.return_type.clone() {
+            None => self.generate_response(expr),
+            Some(ty) => self.generate_value(expr, &ty),
+        };
+        if self.raises {
+            format!("Ok({})", value)
+        } else {
+            value
+        }
+    }
+    
     fn generate_statement(&mut self, stmt: &Statement) {
-        match stmt {
-            Statement::VarDecl { name, var_type, value, is_public } => {
+        match &stmt.kind {
+            StatementKind::VarDecl { name, var_type, value, is_public, doc } => {
                 let visibility = if *is_public { "pub " } else { "" };
-                let ty = var_type.to_rust();
+                let ty = self.use_type(var_type);
+                // Тип элементов пустых `[]` и `{}`, как и тип `None`, Rust вывести не сможет — указываем тип
+                let annotation = match var_type {
+                    DataType::List(_) | DataType::Map(..) | DataType::Json | DataType::Optional(_) => format!(": {}", ty),
+                    _ => String::new(),
+                };
+                
+                // `///` на `let` rustc считает неиспользуемым, поэтому у локальных
+                // переменных документация остаётся обычным комментарием
+                for line in doc {
+                    self.write_line(&format!("// {}", line));
+                }
                 
+                let mutability = self.mutability(name);
+                let local_type = match (var_type, value) {
+                    (DataType::Auto, Some(val)) => self.rust_type(val),
+                    (DataType::Auto, None) => None,
+                    _ => Some(ty.clone()),
+                };
+                if let Some(local_type) = local_type {
+                    self.local_types.insert(name.clone(), local_type);
+                }
                 if let Some(val) = value {
-                    let expr_str = self.generate_expr(val);
-                    self.write_line(&format!("{}let {} = {};", visibility, name, expr_str));
+                    let expr_str = self.generate_value(val, var_type);
+                    self.write_line(&format!("{}let {}{}{} = {};", visibility, mutability, name, annotation, expr_str));
                 } else {
-                    self.write_line(&format!("{}let {}: {};", visibility, name, ty));
+                    self.write_line(&format!("{}let {}{}: {};", visibility, mutability, name, ty));
                 }
             }
-            Statement::Return(Some(expr)) => {
-                let expr_str = self.generate_expr(expr);
-                self.write_line(&format!("{}", expr_str));
-            }
-            Statement::Return(None) => {
-                self.write_line("return;");
-            }
-            Statement::If { condition, body, else_body } => {
-                let cond_str = self.generate_expr(condition);
-                self.write_line(&format!("if {} {{", cond_str));
-                self.indent_level += 1;
-                
-                for s in body {
-                    self.generate_statement(s);
+            StatementKind::Return(value) => self.write_return(value.as_ref()),
+            StatementKind::Raise(name) => self.write_error_exit(&format!("KraitError::{}", name)),
+            StatementKind::Break(label) => self.write_jump("break", label.as_deref()),
+            StatementKind::Continue(label) => self.write_jump("continue", label.as_deref()),
+            StatementKind::If { branches, else_body } => {
+                for (i, (condition, body)) in branches.iter().enumerate() {
+                    let narrowed = self.narrowed(condition, body);
+                    let outer_types: Vec<_> = narrowed
+                        .iter()
+                        .flat_map(|(vars, _)| vars)
+                        .map(|(name, inner)| (name.clone(), self.local_types.insert(name.clone(), inner.clone())))
+                        .collect();
+                    let cond_str = match &narrowed {
+                        Some((vars, rest)) => self.generate_narrowing(vars, rest),
+                        None => self.generate_expr(condition),
+                    };
+                    if i == 0 {
+                        self.write_line(&format!("if {} {{", cond_str));
+                    } else {
+                        self.write_line(&format!("}} else if {} {{", cond_str));
+                    }
+                    self.indent_level += 1;
+                    
+                    for s in body {
+                        self.generate_statement(s);
+                    }
+                    for (name, outer) in outer_types {
+                        if let Some(outer) = outer {
+                            self.local_types.insert(name, outer);
+                        }
+                    }
+                    
+                    self.indent_level -= 1;
                 }
                 
-                self.indent_level -= 1;
-                
                 if let Some(else_stmts) = else_body {
                     self.write_line("} else {");
                     self.indent_level += 1;
@@ -222,93 +667,1936 @@ impl CodeGenerator {
                     self.write_line("}");
                 }
             }
-            Statement::While { condition, body } => {
+            StatementKind::While { condition, body, label } => {
                 let cond_str = self.generate_expr(condition);
-                self.write_line(&format!("while {} {{", cond_str));
+                let label = self.rust_loop_label(label, body);
+                self.write_line(&format!("{}while {} {{", loop_label(&label), cond_str));
+                self.generate_loop_body(label, body);
+            }
+            StatementKind::For { var, iter, body, label } => {
+                let head = self.generate_for_head(var, iter);
+                let label = self.rust_loop_label(label, body);
+                self.write_line(&format!("{}for {} {{", loop_label(&label), head));
+                self.generate_loop_body(label, body);
+            }
+            StatementKind::Try { body, catches, finally } => self.generate_try(body, catches, finally.as_deref()),
+            StatementKind::Error => {
+                self.write_line("compile_error!(\"Krait: ошибка синтаксического анализа\");");
+            }
+            StatementKind::ExprStmt(expr) => {
+                let expr_str = self.generate_expr(expr);
+                self.write_line(&format!("{};", expr_str));
+            }
+            StatementKind::Assign { target, op, value } => {
+                let line = self.generate_assign(target, op.as_deref(), value);
+                self.write_line(&line);
+            }
+            StatementKind::Match { scrutinee, arms } => {
+                let head = self.generate_match_head(scrutinee, arms);
+                self.write_line(&head);
                 self.indent_level += 1;
-                
-                for s in body {
-                    self.generate_statement(s);
+                let enum_name = self.arms_enum(arms);
+                for arm in arms {
+                    let pattern = self.generate_pattern(&arm.pattern, enum_name.as_deref());
+                    match &arm.body {
+                        // Оператор `match` значения не возвращает: ветка вычисляется как оператор
+                        ArmBody::Value(value) => {
+                            let value_str = self.generate_expr(value);
+                            self.write_line(&format!("{} => {{ {}; }}", pattern, value_str));
+                        }
+                        ArmBody::Block(body) if body.is_empty() => self.write_line(&format!("{} => {{}}", pattern)),
+                        ArmBody::Block(body) => {
+                            self.write_line(&format!("{} => {{", pattern));
+                            self.indent_level += 1;
+                            for s in body {
+                                self.generate_statement(s);
+                            }
+                            self.indent_level -= 1;
+                            self.write_line("}");
+                        }
+                    }
                 }
-                
                 self.indent_level -= 1;
                 self.write_line("}");
             }
-            Statement::For { var, start, end, body } => {
-                let start_str = self.generate_expr(start);
-                let end_str = self.generate_expr(end);
-                self.write_line(&format!("for {} in {}..{} {{", var, start_str, end_str));
+        }
+    }
+    
+    /// Метка цикла в Rust: своя метка Krait или `'loop_N`, если `break`/`continue`
+    /// без метки выходят из тела `try` — сквозь блок `'try_N: { ... }` Rust
+    /// разрешает переход только по метке
+    fn rust_loop_label(&self, label: &Option<String>, body: &[Statement]) -> Option<String> {
+        label.as_deref().map(user_label).or_else(|| {
+            jumps_out_of_try(body, false, &self.raising, &self.error_codes).then(|| format!("loop_{}", self.loops.len()))
+        })
+    }
+    
+    fn generate_loop_body(&mut self, label: Option<String>, body: &[Statement]) {
+        self.loops.push(label);
+        self.indent_level += 1;
+        for s in body {
+            self.generate_statement(s);
+        }
+        self.indent_level -= 1;
+        self.loops.pop();
+        self.write_line("}");
+    }
+    
+    /// `try` → `match 'try_N: { тело; Ok(()) } { ветки catch }` и затем `finally`.
+    /// Ошибка в теле прерывает блок `break 'try_N Err(...)`, а `return`, `break` и
+    /// `continue` остаются переходами самой функции. Перед выходом из `try` с
+    /// `finally` его тело подставляется в месте перехода. Если ни тело, ни ветки
+    /// не доходят до конца, `match` тоже не завершается, и `finally` после него не пишется.
+    fn generate_try(&mut self, body: &[Statement], catches: &[CatchClause], finally: Option<&[Statement]>) {
+        let loop_depth = self.loops.len();
+        if let Some(finally) = finally {
+            self.handlers.push(Handler::Finally { body: finally.to_vec(), loop_depth });
+        }
+        
+        let raises = body_raises(body, &self.raising, &self.error_codes);
+        let completes = !ends_diverging(body) || raises && !catches.iter().all(|clause| ends_diverging(&clause.body));
+        if raises {
+            let depth = self.handlers.iter().filter(|h| matches!(h, Handler::Body { .. })).count();
+            let label = format!("try_{}", depth);
+            self.write_line(&format!("match '{}: {{", label));
+            self.indent_level += 1;
+            self.handlers.push(Handler::Body { label, loop_depth });
+            for s in body {
+                self.generate_statement(s);
+            }
+            self.handlers.pop();
+            if !ends_diverging(body) {
+                self.write_line("Ok(())");
+            }
+            self.indent_level -= 1;
+            self.write_line("} {");
+            
+            self.indent_level += 1;
+            if completes {
+                self.write_line("Ok(()) => {}");
+            } else {
+                // Тип `!` у всех веток: `match` можно оставить последним в функции с результатом
+                self.write_line("Ok(()) => unreachable!(),");
+            }
+            for clause in catches {
+                let pattern = match (&clause.code, &clause.var) {
+                    (Some(code), Some(var)) => format!("{} @ KraitError::{}", var, code),
+                    (Some(code), None) => format!("KraitError::{}", code),
+                    (None, Some(var)) => var.clone(),
+                    (None, None) => "_".to_string(),
+                };
+                if let Some(var) = &clause.var {
+                    self.local_types.insert(var.clone(), "KraitError".to_string());
+                }
+                self.write_line(&format!("Err({}) => {{", pattern));
                 self.indent_level += 1;
-                
-                for s in body {
+                for s in &clause.body {
                     self.generate_statement(s);
                 }
-                
                 self.indent_level -= 1;
                 self.write_line("}");
             }
-            Statement::Try { body, catch_body } => {
-                self.write_line("match (|| -> Result<(), Box<dyn std::error::Error>> {");
+            if !catches_everything(catches, &self.error_codes) {
+                // Ошибки, которые не поймала ни одна ветка, уходят дальше
+                self.write_line("Err(err) => {");
                 self.indent_level += 1;
-                
-                for s in body {
-                    self.generate_statement(s);
-                }
-                
-                self.write_line("Ok(())");
+                self.write_error_exit("err");
                 self.indent_level -= 1;
-                self.write_line("})() {");
-                
-                self.indent_level += 1;
-                self.write_line("Ok(_) => {},");
-                self.write_line("Err(_) => {");
-                self.indent_level += 1;
-                
-                for s in catch_body {
+                self.write_line("}");
+            }
+            self.indent_level -= 1;
+            self.write_line("}");
+        } else {
+            // Тело не может завершиться ошибкой, и ветки `catch` не нужны
+            self.write_line("{");
+            self.indent_level += 1;
+            for s in body {
+                self.generate_statement(s);
+            }
+            self.indent_level -= 1;
+            self.write_line("}");
+        }
+        
+        if let Some(finally) = finally {
+            self.handlers.pop();
+            if completes {
+                for s in finally {
                     self.generate_statement(s);
                 }
-                
-                self.indent_level -= 1;
-                self.write_line("}");
-                self.indent_level -= 1;
-                self.write_line("}");
             }
-            Statement::ExprStmt(expr) => {
-                let expr_str = self.generate_expr(expr);
-                self.write_line(&format!("{};", expr_str));
+        }
+    }
+    
+    /// `finally` обработчика `index` в месте выхода из его `try`. Переходы внутри
+    /// `finally` относятся уже к внешним обработчикам.
+    fn write_finally(&mut self, index: usize) {
+        let inner = self.handlers.split_off(index);
+        if let Some(Handler::Finally { body, .. }) = inner.first() {
+            for s in body {
+                self.generate_statement(s);
             }
         }
+        self.handlers.extend(inner);
     }
     
-    fn generate_expr(&self, expr: &Expr) -> String {
-        match expr {
-            Expr::Literal(s) => {
-                if s.starts_with('"') {
-                    format!("\"{}\".to_string()", s.trim_matches('"'))
-                } else {
-                    s.clone()
+    /// Выход с ошибкой `err`: в ближайший `try` или из функции, выполнив по пути `finally`
+    fn write_error_exit(&mut self, err: &str) {
+        for index in (0..self.handlers.len()).rev() {
+            match &self.handlers[index] {
+                Handler::Body { label, .. } => {
+                    let line = format!("break '{} Err({});", label, err);
+                    self.write_line(&line);
+                    return;
                 }
+                Handler::Finally { .. } => self.write_finally(index),
             }
-            Expr::Identifier(name) => name.clone(),
-            Expr::BinaryOp { left, op, right } => {
-                let left_str = self.generate_expr(left);
-                let right_str = self.generate_expr(right);
-                format!("{} {} {}", left_str, op, right_str)
+        }
+        self.write_line(&format!("return Err({});", err));
+    }
+    
+    /// `return`: внутри `try` с `finally` значение вычисляется до него
+    fn write_return(&mut self, value: Option<&Expr>) {
+        let value = match value {
+            Some(expr) => Some(self.generate_return_value(expr)),
+            None if self.raises => Some("Ok(())".to_string()),
+            None => None,
+        };
+        if !self.handlers.iter().any(|h| matches!(h, Handler::Finally { .. })) {
+            match value {
+                Some(value) => self.write_line(&format!("return {};", value)),
+                None => self.write_line("return;"),
             }
-            Expr::FunctionCall { name, args } => {
-                let args_str = args
-                    .iter()
-                    .map(|a| self.generate_expr(a))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("{}({})", name, args_str)
+            return;
+        }
+        self.write_line("{");
+        self.indent_level += 1;
+        if let Some(value) = &value {
+            self.write_line(&format!("let krait_result = {};", value));
+        }
+        for index in (0..self.handlers.len()).rev() {
+            self.write_finally(index);
+        }
+        match value {
+            Some(_) => self.write_line("return krait_result;"),
+            None => self.write_line("return;"),
+        }
+        self.indent_level -= 1;
+        self.write_line("}");
+    }
+    
+    /// `break` / `continue`: выполняет `finally` блоков `try` внутри целевого цикла.
+    /// Из тела `try` переход идёт по метке цикла.
+    fn write_jump(&mut self, keyword: &str, label: Option<&str>) {
+        let label = label.map(user_label);
+        let target = match &label {
+            Some(label) => self.loops.iter().rposition(|l| l.as_ref() == Some(label)),
+            None => self.loops.len().checked_sub(1),
+        };
+        let Some(target) = target else {
+            // Переход вне цикла парсер уже отметил ошибкой
+            self.write_line(&format!("{};", keyword));
+            return;
+        };
+        for index in (0..self.handlers.len()).rev() {
+            if self.handlers[index].loop_depth() <= target {
+                break;
             }
+            if matches!(self.handlers[index], Handler::Finally { .. }) {
+                self.write_finally(index);
+            }
+        }
+        let leaves_try = self
+            .handlers
+            .iter()
+            .any(|h| matches!(h, Handler::Body { .. }) && h.loop_depth() > target);
+        let label = label.or_else(|| self.loops[target].clone().filter(|_| leaves_try));
+        match label {
+            Some(label) => self.write_line(&format!("{} '{};", keyword, label)),
+            None => self.write_line(&format!("{};", keyword)),
         }
     }
-}
+    
+    /// Переменные `T?`, которые условие сужает до `T` внутри ветки проверками
+    /// `x != nil` (в том числе среди частей `a and b`): имена с типами Rust значений
+    /// и остальные части условия. Если ветка присваивает переменной, сужения нет —
+    /// присваивание должно менять саму переменную, а не её копию.
+    fn narrowed<'e>(&self, condition: &'e Expr, body: &[Statement]) -> Option<Narrowing<'e>> {
+        let assigned = assigned_variables(body);
+        let mut vars: Vec<(String, String)> = Vec::new();
+        let mut rest = Vec::new();
+        for part in conjuncts(condition) {
+            let name = match &part.kind {
+                ExprKind::BinaryOp { left, op: BinOp::Ne, right } => match (&left.kind, &right.kind) {
+                    (ExprKind::Identifier(name), ExprKind::Nil) | (ExprKind::Nil, ExprKind::Identifier(name)) => Some(name),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let inner = name
+                .filter(|name| !assigned.contains(*name) && !vars.iter().any(|(seen, _)| seen == *name))
+                .and_then(|name| Some((name.clone(), option_inner(self.local_types.get(name)?)?.to_string())));
+            match inner {
+                Some(var) => vars.push(var),
+                None => rest.push(part),
+            }
+        }
+        (!vars.is_empty()).then_some((vars, rest))
+    }
+    
+    /// `let Some(x) = x` для сужаемых переменных, несколько — через `zip`. Остальные
+    /// части условия проверяет `filter` уже над значениями, поэтому проверки
+    /// `!= nil` выполняются первыми.
+    fn generate_narrowing(&mut self, vars: &[(String, String)], rest: &[&Expr]) -> String {
+        let (mut pattern, mut source, mut copies) = (String::new(), String::new(), String::new());
+        for (name, inner) in vars {
+            let value = if is_copy_type(inner) { name.clone() } else { format!("{}.clone()", name) };
+            if pattern.is_empty() {
+                (pattern, source, copies) = (name.clone(), value, format!("{}.clone()", name));
+            } else {
+                pattern = format!("({}, {})", pattern, name);
+                source = format!("{}.zip({})", source, value);
+                copies = format!("({}, {}.clone())", copies, name);
+            }
+        }
+        if !rest.is_empty() {
+            let check: Vec<_> = rest.iter().map(|part| self.generate_operand(part, BinOp::And, Assoc::Left)).collect();
+            let check = check.join(" && ");
+            source = if vars.iter().all(|(_, inner)| is_copy_type(inner)) {
+                format!("{}.filter(|&{}| {})", source, pattern, check)
+            } else {
+                format!("{}.filter(|{}| {{ let {} = {}; {} }})", source, pattern, pattern, copies, check)
+            };
+        }
+        format!("let Some({}) = {}", pattern, source)
+    }
+    
+    /// `i in 0..n`, `item in xs.clone()`, `(key, value) in m.clone()`. Коллекция
+    /// перебирается по копии: тело цикла может её изменять.
+    fn generate_for_head(&mut self, var: &str, iter: &ForIter) -> String {
+        let (collection, value) = match iter {
+            ForIter::Range { start, end, step, inclusive } => {
+                if let Some(ty) = self.rust_type(start) {
+                    self.local_types.insert(var.to_string(), ty);
+                }
+                return format!("{} in {}", var, self.generate_range(start, end, step.as_deref(), *inclusive));
+            }
+            ForIter::Each { collection, value } => (collection, value),
+        };
+        let collection_type = self.rust_type(collection).unwrap_or_default();
+        let args = generic_args(&collection_type).unwrap_or_default();
+        let is_map = collection_type.starts_with("HashMap<");
+        let (var_type, value_type) = match args.as_slice() {
+            _ if collection_type == "Value" && value.is_some() => (Some("String".to_string()), Some(collection_type.clone())),
+            _ if collection_type == "Value" => (Some(collection_type.clone()), None),
+            [item] if collection_type.starts_with("Vec<") => (Some(item.clone()), None),
+            [key, val] if is_map => (Some(key.clone()), Some(val.clone())),
+            _ => (None, None),
+        };
+        for (name, ty) in [(Some(var), var_type), (value.as_deref(), value_type)] {
+            if let (Some(name), Some(ty)) = (name, ty) {
+                self.local_types.insert(name.to_string(), ty);
+            }
+        }
+        
+        let items = if collection_type == "Value" {
+            // JSON перебирается как массив, а с двумя переменными — как объект
+            let receiver = self.generate_receiver(collection);
+            let view = if value.is_some() { "as_object" } else { "as_array" };
+            format!("{}.{}().cloned().unwrap_or_default()", receiver, view)
+        } else if collection.is_place() {
+            format!("{}.clone()", self.generate_receiver(collection))
+        } else {
+            self.generate_expr(collection)
+        };
+        match value {
+            Some(value) => format!("({}, {}) in {}", var, value, items),
+            None if is_map => {
+                let items = if rust_precedence(collection) < ATOM_PRECEDENCE { format!("({})", items) } else { items };
+                format!("{} in {}.into_keys()", var, items)
+            }
+            None => format!("{} in {}", var, items),
+        }
+    }
+    
+    /// Диапазон `for`. С отрицательным шагом-литералом диапазон идёт от `start`
+    /// вниз через `rev`. Знак шага-выражения известен только во время выполнения:
+    /// направление выбирается там же, оба диапазона приводятся к `Box<dyn Iterator>`.
+    fn generate_range(&mut self, start: &Expr, end: &Expr, step: Option<&Expr>, inclusive: bool) -> String {
+        let op = if inclusive { "..=" } else { ".." };
+        match (step, step.and_then(numeric_value)) {
+            (None, _) | (Some(_), Some((1, false))) => {
+                format!("{}{}{}", self.generate_expr(start), op, self.generate_expr(end))
+            }
+            (Some(_), Some((value, true))) => {
+                let start_str = self.generate_expr(start);
+                let range = self.descending_range(&start_str, end, inclusive);
+                if value == 1 {
+                    format!("({}).rev()", range)
+                } else {
+                    format!("({}).rev().step_by({})", range, value)
+                }
+            }
+            (Some(_), Some((value, false))) => {
+                format!("({}{}{}).step_by({})", self.generate_expr(start), op, self.generate_expr(end), value)
+            }
+            (Some(step), None) => {
+                let item = self.rust_type(start).or_else(|| self.rust_type(end)).unwrap_or_else(|| "i32".to_string());
+                let step_str = self.generate_expr(step);
+                let start_str = self.generate_expr(start);
+                let end_str = self.generate_expr(end);
+                let down = self.descending_range(&start_str, end, inclusive);
+                format!(
+                    "{{ let krait_step = {}; let range: Box<dyn Iterator<Item = {}>> = if krait_step < 0 {{ \
+                     Box::new(({}).rev().step_by((-krait_step) as usize)) }} else {{ \
+                     Box::new(({}{}{}).step_by(krait_step as usize)) }}; range }}",
+                    step_str, item, down, start_str, op, end_str
+                )
+            }
+        }
+    }
+    
+    /// Диапазон для обхода от `start` вниз до `end` через `rev`
+    fn descending_range(&mut self, start_str: &str, end: &Expr, inclusive: bool) -> String {
+        match numeric_value(end) {
+            _ if inclusive => format!("{}..={}", self.generate_expr(end), start_str),
+            // Граница-литерал сдвигается сразу: `10, 0, -1` → `1..=10`
+            Some((bound, false)) => format!("{}..={}", bound + 1, start_str),
+            Some((bound, true)) => format!("{}..={}", 1 - bound as i128, start_str),
+            None => format!("{} + 1..={}", self.generate_operand(end, BinOp::Add, Assoc::Left), start_str),
+        }
+    }
+    
+    /// `match x {`. Строки сравниваются с литералами как `&str`; перечисление,
+    /// из вариантов которого извлекаются поля, копируется, чтобы не переместить его
+    fn generate_match_head(&mut self, scrutinee: &Expr, arms: &[MatchArm]) -> String {
+        let string_patterns = arms
+            .iter()
+            .any(|arm| matches!(&arm.pattern, Pattern::Literal(e) if matches!(e.kind, ExprKind::Literal(Literal::Str(_)))));
+        let binds = arms
+            .iter()
+            .any(|arm| matches!(&arm.pattern, Pattern::Variant { bindings, .. } if !bindings.is_empty()));
+        let scrutinee_str = if string_patterns || self.rust_type(scrutinee).as_deref() == Some("String") {
+            format!("{}.as_str()", self.generate_receiver(scrutinee))
+        } else if binds && matches!(scrutinee.kind, ExprKind::Identifier(_)) {
+            format!("{}.clone()", self.generate_expr(scrutinee))
+        } else {
+            self.generate_expr(scrutinee)
+        };
+        format!("match {} {{", scrutinee_str)
+    }
+    
+    /// Перечисление, по вариантам которого идёт `match`: из первого образца
+    /// с именем перечисления или единственное перечисление с таким вариантом
+    fn arms_enum(&self, arms: &[MatchArm]) -> Option<String> {
+        let qualified = arms.iter().find_map(|arm| match &arm.pattern {
+            Pattern::Variant { enum_name: Some(name), .. } => Some(name.clone()),
+            _ => None,
+        });
+        qualified.or_else(|| {
+            arms.iter().find_map(|arm| match &arm.pattern {
+                Pattern::Variant { enum_name: None, variant, .. } => {
+                    let mut owners = self.enums.values().filter(|e| e.variant(variant).is_some());
+                    let owner = owners.next()?;
+                    owners.next().is_none().then(|| owner.name.clone())
+                }
+                _ => None,
+            })
+        })
+    }
+    
+    /// Образец Rust. Связанные имена получают типы полей варианта.
+    fn generate_pattern(&mut self, pattern: &Pattern, arms_enum: Option<&str>) -> String {
+        match pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Literal(expr) => match &expr.kind {
+                ExprKind::Literal(Literal::Str(s)) => rust_string_literal(s),
+                _ => self.generate_expr(expr),
+            },
+            Pattern::Variant { enum_name, variant, bindings } => {
+                let enum_name = enum_name.as_deref().or(arms_enum).unwrap_or_default().to_string();
+                let fields = self
+                    .enums
+                    .get(&enum_name)
+                    .and_then(|e| e.variant(variant))
+                    .map(|v| v.fields.clone())
+                    .unwrap_or_default();
+                let path = format!("{}::{}", enum_name, variant);
+                if fields.is_empty() {
+                    return path;
+                }
+                if bindings.iter().all(|b| b == "_") {
+                    return format!("{} {{ .. }}", path);
+                }
+                let mut parts = Vec::new();
+                for ((field, ty), binding) in fields.iter().zip(bindings) {
+                    if binding != "_" {
+                        self.local_types.insert(binding.clone(), ty.to_rust());
+                    }
+                    if field == binding {
+                        parts.push(field.clone());
+                    } else {
+                        parts.push(format!("{}: {}", field, binding));
+                    }
+                }
+                format!("{} {{ {} }}", path, parts.join(", "))
+            }
+        }
+    }
+    
+    /// Имя перечисления, если `target` — обращение к нему, а не к переменной
+    fn enum_name<'e>(&self, target: &'e Expr) -> Option<&'e str> {
+        match &target.kind {
+            ExprKind::Identifier(name) if self.enums.contains_key(name) && !self.local_types.contains_key(name) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+    
+    /// Вариант перечисления с полями: аргументы сопоставляются полям по порядку
+    fn generate_variant(&mut self, enum_name: &str, variant: &str, args: &[Expr]) -> String {
+        let fields = self
+            .enums
+            .get(enum_name)
+            .and_then(|e| e.variant(variant))
+            .map(|v| v.fields.clone())
+            .unwrap_or_default();
+        let mut parts = Vec::new();
+        for ((field, ty), arg) in fields.iter().zip(args) {
+            let value = self.generate_value(arg, ty);
+            parts.push(format!("{}: {}", field, value));
+        }
+        if parts.is_empty() {
+            format!("{}::{}", enum_name, variant)
+        } else {
+            format!("{}::{} {{ {} }}", enum_name, variant, parts.join(", "))
+        }
+    }
+    
+    fn generate_expr(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Str(s)) => format!("{}.to_string()", rust_string_literal(s)),
+            ExprKind::Literal(Literal::Int { text, .. } | Literal::Float { text, .. }) => text.clone(),
+            ExprKind::Identifier(name) => name.clone(),
+            ExprKind::Interpolated(parts) => {
+                let mut template = String::new();
+                let mut args = Vec::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(text) => {
+                            template.push_str(&text.replace('{', "{{").replace('}', "}}"));
+                        }
+                        InterpolationPart::Expr(e) => {
+                            // Списки, словари, `Option`, модели и перечисления не реализуют `Display`
+                            let debug = self.rust_type(e).is_some_and(|ty| !is_display_type(&ty));
+                            template.push_str(if debug { "{:?}" } else { "{}" });
+                            args.push(self.generate_expr(e));
+                        }
+                    }
+                }
+                format!("format!({}, {})", rust_string_literal(&template), args.join(", "))
+            }
+            ExprKind::Error => "compile_error!(\"Krait: некорректное выражение\")".to_string(),
+            ExprKind::Nil => "None".to_string(),
+            ExprKind::BinaryOp { left, op: BinOp::Pow, right } => self.generate_pow(left, right),
+            // Значение не может отсутствовать (например, сужено `!= nil`): запасное не нужно
+            ExprKind::BinaryOp { left, op: BinOp::Coalesce, .. } if self.is_present(left) => {
+                let value = self.generate_expr(left);
+                if rust_precedence(left) < ATOM_PRECEDENCE { format!("({})", value) } else { value }
+            }
+            ExprKind::BinaryOp { left, op: BinOp::Coalesce, right } => {
+                let fallback = self.generate_expr(right);
+                let receiver = self.generate_option(left);
+                if self.is_option(right) {
+                    format!("{}.or({})", receiver, fallback)
+                } else {
+                    format!("{}.unwrap_or({})", receiver, fallback)
+                }
+            }
+            ExprKind::BinaryOp { left, op: op @ (BinOp::Eq | BinOp::Ne), right }
+                if matches!(left.kind, ExprKind::Nil) || matches!(right.kind, ExprKind::Nil) =>
+            {
+                let value = if matches!(left.kind, ExprKind::Nil) { right } else { left };
+                let check = if *op == BinOp::Eq { "is_none" } else { "is_some" };
+                format!("{}.{}()", self.generate_receiver(value), check)
+            }
+            ExprKind::BinaryOp { left, op, right } => {
+                let mut left_str = self.generate_operand(left, *op, Assoc::Left);
+                let mut right_str = self.generate_operand(right, *op, Assoc::Right);
+                // `x / 2` для дробного `x`: Rust не смешивает `f64` и целый литерал
+                let is_float = |side: &Expr| self.rust_type(side).is_some_and(|ty| is_float_type(&ty));
+                if is_float(left) {
+                    right_str = float_literal(right).unwrap_or(right_str);
+                } else if is_float(right) {
+                    left_str = float_literal(left).unwrap_or(left_str);
+                }
+                // `x == 5` для `int?`: значение сравнивается с `Some(5)`
+                if matches!(op, BinOp::Eq | BinOp::Ne) {
+                    match (self.is_option(left), self.is_option(right)) {
+                        (true, false) => right_str = format!("Some({})", self.generate_expr(right)),
+                        (false, true) => left_str = format!("Some({})", self.generate_expr(left)),
+                        _ => {}
+                    }
+                }
+                format!("{} {} {}", left_str, op.to_rust(), right_str)
+            }
+            ExprKind::Unary { op, operand } => {
+                let operand_str = self.generate_expr(operand);
+                // `-(-x)`: `--x` rustc считает опечаткой декремента
+                let nested_neg = *op == UnaryOp::Neg && matches!(operand.kind, ExprKind::Unary { op: UnaryOp::Neg, .. });
+                if rust_precedence(operand) < UNARY_PRECEDENCE || nested_neg {
+                    format!("{}({})", op.to_rust(), operand_str)
+                } else {
+                    format!("{}{}", op.to_rust(), operand_str)
+                }
+            }
+            ExprKind::List(items) => {
+                let items_str = items.iter().map(|i| self.generate_expr(i)).collect::<Vec<_>>().join(", ");
+                format!("vec![{}]", items_str)
+            }
+            ExprKind::Index { target, index } => {
+                let place = self.generate_index(target, index);
+                // Элемент копируется из списка: для не-`Copy` типов нужен `clone`
+                match self.rust_type(expr) {
+                    Some(ty) if is_copy_type(&ty) => place,
+                    _ => format!("{}.clone()", place),
+                }
+            }
+            ExprKind::Field { target, name } => {
+                if let Some(module) = self.module_alias(target).or(self.enum_name(target)) {
+                    return format!("{}::{}", module, name);
+                }
+                if self.is_error(target) {
+                    // Ошибка из `catch err`: `err.code` и `err.message`
+                    let receiver = self.generate_receiver(target);
+                    return match name.as_str() {
+                        "message" => format!("{}.message().to_string()", receiver),
+                        _ => format!("{}.{}()", receiver, name),
+                    };
+                }
+                let place = self.generate_place(expr);
+                match self.rust_type(expr) {
+                    Some(ty) if is_copy_type(&ty) => place,
+                    _ => format!("{}.clone()", place),
+                }
+            }
+            ExprKind::MethodCall { target, name, args } if self.enum_name(target).is_some() => {
+                let enum_name = self.enum_name(target).unwrap_or_default().to_string();
+                self.generate_variant(&enum_name, name, args)
+            }
+            // `m.contains(k)` и `xs.contains(x)` — то же, что `contains(m, k)`
+            ExprKind::MethodCall { target, name, args } if name == "contains" && args.len() == 1
+                && self.rust_type(target).is_some_and(|ty| ty.starts_with("HashMap<") || ty.starts_with("Vec<")) =>
+            {
+                self.generate_contains(target, &args[0])
+            }
+            ExprKind::MethodCall { target, name, args } => {
+                let args_str = args.iter().map(|a| self.generate_expr(a)).collect::<Vec<_>>().join(", ");
+                if let Some(module) = self.module_alias(target) {
+                    return format!("{}::{}({})", module, name, args_str);
+                }
+                // Методы строк принимают образец `&str`, а не `String`
+                let args_str = if self.rust_type(target).as_deref() == Some("String") {
+                    args.iter()
+                        .map(|a| match &a.kind {
+                            ExprKind::Literal(Literal::Str(s)) => rust_string_literal(s),
+                            _ => self.generate_expr(a),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                } else {
+                    args_str
+                };
+                let receiver = self.generate_receiver(target);
+                // Длины в Krait — `int`
+                if name == "len" && args.is_empty() {
+                    format!("{}.len() as i32", receiver)
+                } else {
+                    format!("{}.{}({})", receiver, name, args_str)
+                }
+            }
+            ExprKind::Map(entries) => {
+                self.required_libs.insert("collections".to_string());
+                if entries.is_empty() {
+                    return "HashMap::new()".to_string();
+                }
+                let entries_str = entries
+                    .iter()
+                    .map(|(key, value)| format!("({}, {})", self.generate_expr(key), self.generate_expr(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("HashMap::from([{}])", entries_str)
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let mut lines = vec![self.generate_match_head(scrutinee, arms)];
+                let enum_name = self.arms_enum(arms);
+                let indent = self.indent();
+                self.indent_level += 1;
+                for arm in arms {
+                    let pattern = self.generate_pattern(&arm.pattern, enum_name.as_deref());
+                    // Ветки-блоки в выражении парсер не пропускает
+                    if let ArmBody::Value(value) = &arm.body {
+                        let value_str = self.generate_expr(value);
+                        lines.push(format!("{}{} => {},", self.indent(), pattern, value_str));
+                    }
+                }
+                self.indent_level -= 1;
+                lines.push(format!("{}}}", indent));
+                lines.join("\n")
+            }
+            ExprKind::Construct { model, fields } => {
+                let field_types: HashMap<String, DataType> = self
+                    .models
+                    .get(model)
+                    .map(|m| m.fields.iter().map(|f| (f.name.clone(), f.field_type.clone())).collect())
+                    .unwrap_or_default();
+                let mut parts = Vec::new();
+                for (name, value) in fields {
+                    let value_str = match field_types.get(name) {
+                        Some(ty) => self.generate_value(value, ty),
+                        None => self.generate_expr(value),
+                    };
+                    parts.push(format!("{}: {}", name, value_str));
+                }
+                // Пропущенные поля берутся из `Default`
+                let complete = self
+                    .models
+                    .get(model)
+                    .is_some_and(|m| m.fields.iter().all(|f| fields.iter().any(|(name, _)| name == &f.name)));
+                if parts.is_empty() {
+                    return if complete { format!("{} {{}}", model) } else { format!("{}::default()", model) };
+                }
+                if !complete {
+                    parts.push("..Default::default()".to_string());
+                }
+                format!("{} {{ {} }}", model, parts.join(", "))
+            }
+            ExprKind::FunctionCall { name, args } if name == "len" && args.len() == 1
+                && !self.function_types.contains_key(name) =>
+            {
+                format!("{}.len() as i32", self.generate_receiver(&args[0]))
+            }
+            ExprKind::FunctionCall { name, args } if name == "contains" && args.len() == 2
+                && !self.function_types.contains_key(name) =>
+            {
+                self.generate_contains(&args[0], &args[1])
+            }
+            ExprKind::FunctionCall { name, args } => {
+                let params = self.function_params.get(name).cloned().unwrap_or_default();
+                let args_str = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| match params.get(i) {
+                        Some(ty) => self.generate_value(a, ty),
+                        None => self.generate_expr(a),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let call = format!("{}({})", name, args_str);
+                if !self.raising.contains(name) {
+                    call
+                } else if self.handlers.is_empty() {
+                    // Ошибку вызванной функции передаём дальше: вызывающая тоже возвращает `Result`
+                    if self.raises {
+                        format!("{}?", call)
+                    } else {
+                        call
+                    }
+                } else {
+                    self.generate_caught_call(&call)
+                }
+            }
+        }
+    }
+    
+    /// Вызов функции с `raise` внутри `try`: ошибка уходит в его ветки `catch`
+    fn generate_caught_call(&mut self, call: &str) -> String {
+        let output = std::mem::take(&mut self.output);
+        self.indent_level += 2;
+        self.write_error_exit("err");
+        self.indent_level -= 2;
+        let exit = std::mem::replace(&mut self.output, output);
+        let indent = self.indent();
+        match exit.trim().strip_suffix(';') {
+            Some(exit) if !exit.contains('\n') => {
+                format!("match {} {{ Ok(value) => value, Err(err) => {} }}", call, exit)
+            }
+            _ => format!(
+                "match {} {{\n{i}    Ok(value) => value,\n{i}    Err(err) => {{\n{}{i}    }}\n{i}}}",
+                call,
+                exit,
+                i = indent
+            ),
+        }
+    }
+    
+    /// Значение ошибки `KraitError`, пойманной в `catch`
+    fn is_error(&self, expr: &Expr) -> bool {
+        self.rust_type(expr).is_some_and(|ty| ty == "KraitError")
+    }
+    
+    /// Операнд бинарного оператора `op` со стороны `side`. Скобки ставятся по той же
+    /// таблице приоритетов, что и при разборе, поэтому смысл выражения сохраняется.
+    fn generate_operand(&mut self, operand: &Expr, op: BinOp, side: Assoc) -> String {
+        let operand_str = self.generate_place(operand);
+        let precedence = rust_precedence(operand);
+        // `x as i32 < y` Rust читает как начало `i32<...>`
+        let cast_before_less = precedence == CAST_PRECEDENCE && op == BinOp::Lt && side == Assoc::Left;
+        let needs_parens = precedence < op.precedence()
+            || (precedence == op.precedence() && op.assoc() != side)
+            || cast_before_less;
+        if needs_parens {
+            format!("({})", operand_str)
+        } else {
+            operand_str
+        }
+    }
+    
+    /// Имя импортированного модуля, если `target` обращается к нему, а не к переменной
+    fn module_alias<'e>(&self, target: &'e Expr) -> Option<&'e str> {
+        match &target.kind {
+            ExprKind::Identifier(name) if self.modules.contains_key(name) && !self.local_types.contains_key(name) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+    
+    /// Место в памяти без копирования: цель присваивания, объект метода,
+    /// операнд оператора. Элемент списка и поле здесь не клонируются.
+    fn generate_place(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Index { target, index } => self.generate_index(target, index),
+            ExprKind::Field { target, name }
+                if self.module_alias(target).is_none() && self.enum_name(target).is_none() && !self.is_error(target) =>
+            {
+                format!("{}.{}", self.generate_receiver(target), name)
+            }
+            _ => self.generate_expr(expr),
+        }
+    }
+    
+    /// Выражение перед `.` или `[`: в скобках, если это не атом
+    fn generate_receiver(&mut self, expr: &Expr) -> String {
+        let expr_str = self.generate_place(expr);
+        if rust_precedence(expr) < ATOM_PRECEDENCE {
+            format!("({})", expr_str)
+        } else {
+            expr_str
+        }
+    }
+    
+    /// `&expr`; выражения слабее унарных операторов берутся в скобки
+    fn generate_ref(&mut self, expr: &Expr) -> String {
+        let expr_str = self.generate_expr(expr);
+        if rust_precedence(expr) < UNARY_PRECEDENCE {
+            format!("&({})", expr_str)
+        } else {
+            format!("&{}", expr_str)
+        }
+    }
+    
+    /// Ключ для поиска в словаре: строковый литерал передаётся как `&str`, остальное — по ссылке
+    fn generate_key(&mut self, key: &Expr) -> String {
+        match &key.kind {
+            ExprKind::Literal(Literal::Str(s)) => rust_string_literal(s),
+            _ => self.generate_ref(key),
+        }
+    }
+    
+    /// `xs[i]` → `xs[i as usize]`, `m[k]` → `m[&k]`; индекс-литерал Rust приводит сам
+    fn generate_index(&mut self, target: &Expr, index: &Expr) -> String {
+        let target_type = self.rust_type(target).unwrap_or_default();
+        let string_key = matches!(index.kind, ExprKind::Literal(Literal::Str(_)) | ExprKind::Interpolated(_))
+            || self.rust_type(index).as_deref() == Some("String");
+        let target_str = self.generate_receiver(target);
+        if target_type.starts_with("HashMap<") || (target_type == "Value" && string_key) {
+            return format!("{}[{}]", target_str, self.generate_key(index));
+        }
+        let index_str = self.generate_expr(index);
+        let untyped_literal = matches!(index.kind, ExprKind::Literal(Literal::Int { suffix: None, .. }));
+        if untyped_literal || self.rust_type(index).as_deref() == Some("usize") {
+            format!("{}[{}]", target_str, index_str)
+        } else if rust_precedence(index) < UNARY_PRECEDENCE {
+            format!("{}[({}) as usize]", target_str, index_str)
+        } else {
+            format!("{}[{} as usize]", target_str, index_str)
+        }
+    }
+    
+    /// Присваивание. В словарь значение добавляется через `insert`: у `HashMap`
+    /// нет `IndexMut`, а составное присваивание требует, чтобы ключ уже был.
+    fn generate_assign(&mut self, target: &Expr, op: Option<&str>, value: &Expr) -> String {
+        if let ExprKind::Index { target: map, index: key } = &target.kind {
+            let map_type = self.rust_type(map).unwrap_or_default();
+            if map_type.starts_with("HashMap<") {
+                let map_str = self.generate_receiver(map);
+                let value_str = self.generate_expr(value);
+                return match op {
+                    None => format!("{}.insert({}, {});", map_str, self.generate_expr(key), value_str),
+                    Some(op) => format!(
+                        "*{}.get_mut({}).expect(\"Krait: ключ не найден в словаре\") {}= {};",
+                        map_str,
+                        self.generate_key(key),
+                        op,
+                        value_str
+                    ),
+                };
+            }
+            if map_type == "Value" && op.is_none() {
+                let place = self.generate_place(target);
+                return format!("{} = {};", place, self.generate_json(value));
+            }
+        }
+        let target_str = self.generate_place(target);
+        let wraps = op.is_none() && !matches!(value.kind, ExprKind::Nil) && self.is_option(target) && !self.is_option(value);
+        let to_float = self.rust_type(target).is_some_and(|ty| is_float_type(&ty));
+        let value_str = match float_literal(value).filter(|_| to_float) {
+            Some(literal) => literal,
+            None => self.generate_expr(value),
+        };
+        let value_str = if wraps { format!("Some({})", value_str) } else { value_str };
+        format!("{} {}= {};", target_str, op.unwrap_or(""), value_str)
+    }
+    
+    /// Значение для места типа `ty`: `json!` для `json`, `Some(...)` для `T?`
+    fn generate_value(&mut self, expr: &Expr, ty: &DataType) -> String {
+        match ty {
+            DataType::Json => self.generate_json(expr),
+            DataType::Float | DataType::Double if float_literal(expr).is_some() => float_literal(expr).unwrap_or_default(),
+            DataType::Optional(inner) => {
+                if matches!(expr.kind, ExprKind::Nil) || self.is_option(expr) {
+                    self.generate_expr(expr)
+                } else {
+                    format!("Some({})", self.generate_value(expr, inner))
+                }
+            }
+            _ => self.generate_expr(expr),
+        }
+    }
+    
+    /// Проверка вхождения: ключа в словарь или элемента в список
+    fn generate_contains(&mut self, collection: &Expr, item: &Expr) -> String {
+        let is_map = self.rust_type(collection).is_some_and(|ty| ty.starts_with("HashMap<"));
+        let receiver = self.generate_receiver(collection);
+        if is_map {
+            format!("{}.contains_key({})", receiver, self.generate_key(item))
+        } else {
+            format!("{}.contains({})", receiver, self.generate_ref(item))
+        }
+    }
+    
+    /// Значение необязательного типа `Option<T>`
+    fn is_option(&self, expr: &Expr) -> bool {
+        self.rust_type(expr).is_some_and(|ty| option_inner(&ty).is_some())
+    }
+    
+    /// Значение известного типа, который не `Option<T>`
+    fn is_present(&self, expr: &Expr) -> bool {
+        self.rust_type(expr).is_some_and(|ty| option_inner(&ty).is_none())
+    }
+    
+    /// Необязательное значение перед `.unwrap_or` и т.п.: переменная с не-`Copy`
+    /// содержимым копируется, чтобы её можно было использовать дальше
+    fn generate_option(&mut self, expr: &Expr) -> String {
+        let receiver = self.generate_receiver(expr);
+        let copy = self
+            .rust_type(expr)
+            .is_some_and(|ty| option_inner(&ty).is_some_and(is_copy_type));
+        if expr.is_place() && !copy {
+            format!("{}.clone()", receiver)
+        } else {
+            receiver
+        }
+    }
+    
+    /// Значение JSON: литералы словарей и списков становятся объектами и массивами `json!`
+    fn generate_json(&mut self, expr: &Expr) -> String {
+        if self.rust_type(expr).as_deref() == Some("Value") {
+            return self.generate_expr(expr);
+        }
+        self.required_libs.insert("json".to_string());
+        format!("json!({})", self.json_value(expr))
+    }
+    
+    fn json_value(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Map(entries) => {
+                let entries_str = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let key_str = match &key.kind {
+                            ExprKind::Literal(Literal::Str(s)) => rust_string_literal(s),
+                            _ => format!("({})", self.generate_expr(key)),
+                        };
+                        format!("{}: {}", key_str, self.json_value(value))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{{{}}}", entries_str)
+            }
+            ExprKind::List(items) => {
+                let items_str = items.iter().map(|i| self.json_value(i)).collect::<Vec<_>>().join(", ");
+                format!("[{}]", items_str)
+            }
+            ExprKind::Literal(Literal::Str(s)) => rust_string_literal(s),
+            _ => self.generate_expr(expr),
+        }
+    }
+    
+    /// Ответ обработчика маршрута на `return`: словари, списки и JSON отдаются
+    /// как `application/json`, остальное — телом ответа
+    fn generate_response(&mut self, expr: &Expr) -> String {
+        if matches!(expr.kind, ExprKind::Map(_) | ExprKind::List(_)) {
+            return format!("HttpResponse::Ok().json({})", self.generate_json(expr));
+        }
+        let is_json = self
+            .rust_type(expr)
+            .is_some_and(|ty| {
+                ty == "Value" || ty.starts_with("HashMap<") || ty.starts_with("Vec<") || self.models.contains_key(&ty)
+            });
+        let expr_str = self.generate_expr(expr);
+        if is_json {
+            format!("HttpResponse::Ok().json({})", expr_str)
+        } else {
+            format!("HttpResponse::Ok().body({})", expr_str)
+        }
+    }
+    
+    /// `a ** b` → `i32::pow(a, b as u32)`, `f64::powi(a, b)` или `f64::powf(a, b)`
+    /// в зависимости от типов операндов
+    fn generate_pow(&mut self, base: &Expr, exponent: &Expr) -> String {
+        let base_type = self.rust_type(base).unwrap_or_else(|| "i32".to_string());
+        let exponent_type = self.rust_type(exponent).unwrap_or_else(|| "i32".to_string());
+        let base_str = self.generate_expr(base);
+        let exponent_str = self.generate_expr(exponent);
+        let (method, exponent_target) = if !is_float_type(&base_type) {
+            ("pow", "u32")
+        } else if is_float_type(&exponent_type) {
+            ("powf", base_type.as_str())
+        } else {
+            ("powi", "i32")
+        };
+        // Литерал без суффикса Rust сам выведет нужного типа
+        let untyped_literal = matches!(exponent.kind, ExprKind::Literal(Literal::Int { suffix: None, .. }))
+            && !is_float_type(exponent_target);
+        let exponent_str = if exponent_type == exponent_target || untyped_literal {
+            exponent_str
+        } else if rust_precedence(exponent) < UNARY_PRECEDENCE {
+            format!("({}) as {}", exponent_str, exponent_target)
+        } else {
+            format!("{} as {}", exponent_str, exponent_target)
+        };
+        format!("{}::{}({}, {})", base_type, method, base_str, exponent_str)
+    }
+    
+    /// Тип Rust, который получит выражение, если его можно определить без вывода типов
+    fn rust_type(&self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int { suffix, .. }) => Some(suffix.clone().unwrap_or_else(|| "i32".to_string())),
+            ExprKind::Literal(Literal::Float { suffix, .. }) => Some(suffix.clone().unwrap_or_else(|| "f64".to_string())),
+            ExprKind::Literal(Literal::Str(_)) | ExprKind::Interpolated(_) => Some("String".to_string()),
+            ExprKind::Identifier(name) => self.local_types.get(name).or_else(|| self.constants.get(name)).cloned(),
+            ExprKind::FunctionCall { name, .. } => match self.function_types.get(name) {
+                Some(DataType::Auto) => None,
+                Some(ty) => Some(ty.to_rust()),
+                None if name == "len" => Some("i32".to_string()),
+                None if name == "contains" => Some("bool".to_string()),
+                None => None,
+            },
+            ExprKind::List(items) => items.iter().find_map(|i| self.rust_type(i)).map(|ty| format!("Vec<{}>", ty)),
+            ExprKind::Map(entries) => entries.iter().find_map(|(key, value)| {
+                Some(format!("HashMap<{}, {}>", self.rust_type(key)?, self.rust_type(value)?))
+            }),
+            ExprKind::Index { target, .. } => {
+                let target_type = self.rust_type(target)?;
+                if target_type == "Value" {
+                    return Some(target_type);
+                }
+                let args = generic_args(&target_type)?;
+                match args.as_slice() {
+                    [item] if target_type.starts_with("Vec<") => Some(item.clone()),
+                    [_, value] if target_type.starts_with("HashMap<") => Some(value.clone()),
+                    _ => None,
+                }
+            }
+            ExprKind::BinaryOp { op: BinOp::Coalesce, left, .. } if self.is_present(left) => self.rust_type(left),
+            ExprKind::BinaryOp { op: BinOp::Coalesce, left, right } => {
+                let fallback = self.rust_type(right);
+                match fallback {
+                    Some(ty) if option_inner(&ty).is_some() => Some(ty),
+                    fallback => self.rust_type(left).and_then(|ty| option_inner(&ty).map(str::to_string)).or(fallback),
+                }
+            }
+            ExprKind::BinaryOp { op, left, right } => match op.precedence() {
+                p if p <= BinOp::Eq.precedence() => Some("bool".to_string()),
+                // `2 * x`: целый литерал слева становится дробным, если `x` дробный
+                _ => match self.rust_type(right) {
+                    Some(ty) if is_float_type(&ty) => Some(ty),
+                    right_type => self.rust_type(left).or(right_type),
+                },
+            },
+            ExprKind::Unary { op: UnaryOp::Not, .. } => Some("bool".to_string()),
+            ExprKind::Unary { operand, .. } => self.rust_type(operand),
+            ExprKind::MethodCall { name, args, .. } if name == "len" && args.is_empty() => Some("i32".to_string()),
+            ExprKind::MethodCall { name, args, .. } if name == "contains" && args.len() == 1 => Some("bool".to_string()),
+            ExprKind::Field { target, .. } | ExprKind::MethodCall { target, .. } if self.enum_name(target).is_some() => {
+                self.enum_name(target).map(str::to_string)
+            }
+            ExprKind::Match { arms, .. } => arms.iter().find_map(|arm| match &arm.body {
+                ArmBody::Value(value) => self.rust_type(value),
+                ArmBody::Block(_) => None,
+            }),
+            ExprKind::Field { target, name } if self.is_error(target) => match name.as_str() {
+                "code" => Some("i32".to_string()),
+                "message" => Some("String".to_string()),
+                _ => None,
+            },
+            ExprKind::Field { target, name } => {
+                let model = self.models.get(&self.rust_type(target)?)?;
+                let field = model.fields.iter().find(|f| &f.name == name)?;
+                Some(field.field_type.to_rust())
+            }
+            ExprKind::Construct { model, .. } => Some(model.clone()),
+            ExprKind::MethodCall { .. } => None,
+            ExprKind::Nil | ExprKind::Error => None,
+        }
+    }
+}
+
+/// Целочисленный литерал, возможно под минусом: значение и знак
+fn numeric_value(expr: &Expr) -> Option<(u128, bool)> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Int { value, .. }) => Some((*value, false)),
+        ExprKind::Unary { op: UnaryOp::Neg, operand } => match &operand.kind {
+            ExprKind::Literal(Literal::Int { value, .. }) => Some((*value, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Целый литерал без суффикса, записанный как дробный: `7` → `7.0`, `-2` → `-2.0`
+fn float_literal(expr: &Expr) -> Option<String> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Int { value, suffix: None, .. }) => Some(format!("{}.0", value)),
+        ExprKind::Unary { op: UnaryOp::Neg, operand } => float_literal(operand).map(|literal| format!("-{}", literal)),
+        _ => None,
+    }
+}
+
+/// Метка Rust для метки Krait: префикс не даёт ей совпасть с `'static`
+/// или с метками `'loop_N`, `'try_N` самого генератора
+fn user_label(label: &str) -> String {
+    format!("k_{}", label)
+}
+
+/// Метка цикла Rust `'k_outer: `
+fn loop_label(label: &Option<String>) -> String {
+    label.as_ref().map(|label| format!("'{}: ", label)).unwrap_or_default()
+}
+
+/// Приоритет сгенерированного выражения в Rust. `**` превращается в вызов
+/// `pow` и в скобках не нуждается.
+fn rust_precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        ExprKind::BinaryOp { op: BinOp::Pow | BinOp::Coalesce, .. } => ATOM_PRECEDENCE,
+        ExprKind::BinaryOp { left, op: BinOp::Eq | BinOp::Ne, right }
+            if matches!(left.kind, ExprKind::Nil) || matches!(right.kind, ExprKind::Nil) =>
+        {
+            ATOM_PRECEDENCE
+        }
+        ExprKind::FunctionCall { name, args } if name == "len" && args.len() == 1 => CAST_PRECEDENCE,
+        ExprKind::MethodCall { name, args, .. } if name == "len" && args.is_empty() => CAST_PRECEDENCE,
+        _ => expr.precedence(),
+    }
+}
+
+/// `x as T` в Rust связывает сильнее `*`, но слабее унарных операторов. В таблице
+/// Krait между ними нет уровня, поэтому приведение стоит на уровне `*`: справа
+/// от `*` скобки лишние, но безопасные.
+const CAST_PRECEDENCE: u8 = UNARY_PRECEDENCE - 1;
+
+fn is_float_type(rust_type: &str) -> bool {
+    matches!(rust_type, "f32" | "f64")
+}
+
+/// Параметры обобщённого типа: `HashMap<String, Vec<i32>>` → `["String", "Vec<i32>"]`
+fn generic_args(rust_type: &str) -> Option<Vec<String>> {
+    let inner = &rust_type[rust_type.find('<')? + 1..rust_type.strip_suffix('>')?.len()];
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, ch) in inner.char_indices() {
+        match ch {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(inner[start..].trim().to_string());
+    Some(args)
+}
+
+/// `T` для `Option<T>`
+fn option_inner(rust_type: &str) -> Option<&str> {
+    rust_type.strip_prefix("Option<")?.strip_suffix('>')
+}
+
+/// Сужение условия ветки: переменные `T?` с типами Rust значений и остальные части условия
+type Narrowing<'e> = (Vec<(String, String)>, Vec<&'e Expr>);
+
+/// Части условия `a and b and c`
+fn conjuncts(condition: &Expr) -> Vec<&Expr> {
+    match &condition.kind {
+        ExprKind::BinaryOp { left, op: BinOp::And, right } => {
+            let mut parts = conjuncts(left);
+            parts.extend(conjuncts(right));
+            parts
+        }
+        _ => vec![condition],
+    }
+}
+
+/// Примитивные типы, которые Rust копирует без `clone`
+fn is_copy_type(rust_type: &str) -> bool {
+    matches!(
+        rust_type,
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+            | "f32" | "f64" | "bool"
+    )
+}
+
+/// Тип, который `format!` выводит через `{}`; остальные выводятся через `{:?}`
+fn is_display_type(rust_type: &str) -> bool {
+    is_copy_type(rust_type) || matches!(rust_type, "String" | "&str" | "Value")
+}
+
+/// Методы, которые изменяют свой объект: переменная, у которой они вызываются,
+/// объявляется `let mut`
+const MUTATING_METHODS: &[&str] = &[
+    "push", "pop", "insert", "remove", "clear", "sort", "sort_unstable", "reverse", "extend",
+    "truncate", "retain", "dedup", "push_str", "append", "drain", "swap",
+];
+
+/// Обходит все операторы `body`, включая вложенные блоки
+fn for_each_statement(body: &[Statement], f: &mut dyn FnMut(&Statement)) {
+    for stmt in body {
+        f(stmt);
+        for block in child_blocks(stmt) {
+            for_each_statement(block, f);
+        }
+    }
+}
+
+/// Блоки, непосредственно вложенные в оператор
+fn child_blocks(stmt: &Statement) -> Vec<&[Statement]> {
+    match &stmt.kind {
+        StatementKind::If { branches, else_body } => branches
+            .iter()
+            .map(|(_, body)| body.as_slice())
+            .chain(else_body.as_deref())
+            .collect(),
+        StatementKind::While { body, .. } | StatementKind::For { body, .. } => vec![body],
+        StatementKind::Try { body, catches, finally } => std::iter::once(body.as_slice())
+            .chain(catches.iter().map(|clause| clause.body.as_slice()))
+            .chain(finally.as_deref())
+            .collect(),
+        StatementKind::Match { arms, .. } => arms
+            .iter()
+            .filter_map(|arm| match &arm.body {
+                ArmBody::Block(body) => Some(body.as_slice()),
+                ArmBody::Value(_) => None,
+            })
+            .collect(),
+        StatementKind::VarDecl { .. } | StatementKind::Return(_) | StatementKind::ExprStmt(_)
+        | StatementKind::Assign { .. } | StatementKind::Break(_) | StatementKind::Continue(_)
+        | StatementKind::Raise(_) | StatementKind::Error => Vec::new(),
+    }
+}
+
+/// Обходит все выражения `body`, включая вложенные
+fn for_each_expr(body: &[Statement], f: &mut dyn FnMut(&Expr)) {
+    for_each_statement(body, &mut |stmt| statement_exprs(stmt, f));
+}
+
+/// Обходит выражения самого оператора (без вложенных блоков)
+fn statement_exprs(stmt: &Statement, f: &mut dyn FnMut(&Expr)) {
+    match &stmt.kind {
+        StatementKind::VarDecl { value: Some(e), .. }
+        | StatementKind::Return(Some(e))
+        | StatementKind::While { condition: e, .. }
+        | StatementKind::ExprStmt(e) => visit_expr(e, f),
+        StatementKind::If { branches, .. } => branches.iter().for_each(|(condition, _)| visit_expr(condition, f)),
+        StatementKind::For { iter: ForIter::Range { start, end, step, .. }, .. } => {
+            visit_expr(start, f);
+            visit_expr(end, f);
+            if let Some(step) = step {
+                visit_expr(step, f);
+            }
+        }
+        StatementKind::For { iter: ForIter::Each { collection, .. }, .. } => visit_expr(collection, f),
+        StatementKind::Assign { target, value, .. } => {
+            visit_expr(target, f);
+            visit_expr(value, f);
+        }
+        StatementKind::Match { scrutinee, arms } => {
+            visit_expr(scrutinee, f);
+            visit_arms(arms, f);
+        }
+        StatementKind::VarDecl { value: None, .. } | StatementKind::Return(None) | StatementKind::Try { .. }
+        | StatementKind::Break(_) | StatementKind::Continue(_) | StatementKind::Raise(_) | StatementKind::Error => {}
+    }
+}
+
+/// Значения веток и литералы образцов; блоки веток обходит `for_each_statement`
+fn visit_arms(arms: &[MatchArm], f: &mut dyn FnMut(&Expr)) {
+    for arm in arms {
+        if let Pattern::Literal(e) = &arm.pattern {
+            visit_expr(e, f);
+        }
+        if let ArmBody::Value(e) = &arm.body {
+            visit_expr(e, f);
+        }
+    }
+}
+
+/// Обходит выражение и все вложенные в него
+fn visit_expr(expr: &Expr, f: &mut dyn FnMut(&Expr)) {
+    f(expr);
+    match &expr.kind {
+        ExprKind::BinaryOp { left, right, .. } => {
+            visit_expr(left, f);
+            visit_expr(right, f);
+        }
+        ExprKind::Unary { operand: target, .. } | ExprKind::Field { target, .. } => visit_expr(target, f),
+        ExprKind::FunctionCall { args: items, .. } | ExprKind::List(items) => {
+            items.iter().for_each(|e| visit_expr(e, f));
+        }
+        ExprKind::MethodCall { target, args, .. } => {
+            visit_expr(target, f);
+            args.iter().for_each(|e| visit_expr(e, f));
+        }
+        ExprKind::Index { target, index } => {
+            visit_expr(target, f);
+            visit_expr(index, f);
+        }
+        ExprKind::Map(entries) => {
+            for (key, value) in entries {
+                visit_expr(key, f);
+                visit_expr(value, f);
+            }
+        }
+        ExprKind::Construct { fields, .. } => fields.iter().for_each(|(_, e)| visit_expr(e, f)),
+        ExprKind::Match { scrutinee, arms } => {
+            visit_expr(scrutinee, f);
+            visit_arms(arms, f);
+        }
+        ExprKind::Interpolated(parts) => {
+            for part in parts {
+                if let InterpolationPart::Expr(e) = part {
+                    visit_expr(e, f);
+                }
+            }
+        }
+        ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Nil | ExprKind::Error => {}
+    }
+}
+
+/// Функции, которые могут завершиться `raise`: сами содержат `raise` или вызывают
+/// такие функции. Вызовы распространяют ошибку, пока множество не перестанет расти.
+fn raising_functions(functions: &[&FunctionDef], error_codes: &[String]) -> HashSet<String> {
+    let mut raising = HashSet::new();
+    loop {
+        let found: Vec<String> = functions
+            .iter()
+            .filter(|func| !raising.contains(&func.name) && body_raises(&func.body, &raising, error_codes))
+            .map(|func| func.name.clone())
+            .collect();
+        if found.is_empty() {
+            return raising;
+        }
+        raising.extend(found);
+    }
+}
+
+/// Перехватывают ли ветки `catch` любую ошибку: есть общая ветка или
+/// ветки перечисляют все объявленные коды `error_codes`
+fn catches_everything(catches: &[CatchClause], error_codes: &[String]) -> bool {
+    catches.iter().any(|clause| clause.code.is_none())
+        || error_codes.iter().all(|code| catches.iter().any(|clause| clause.code.as_ref() == Some(code)))
+}
+
+/// Может ли `body` завершиться ошибкой: содержит `raise` или вызов функции из `raising`
+fn body_raises(body: &[Statement], raising: &HashSet<String>, error_codes: &[String]) -> bool {
+    body.iter().any(|stmt| {
+        let mut found = matches!(stmt.kind, StatementKind::Raise(_));
+        statement_exprs(stmt, &mut |e| {
+            found |= matches!(&e.kind, ExprKind::FunctionCall { name, .. } if raising.contains(name))
+        });
+        match &stmt.kind {
+            // Ошибки из тела `try` не уходят дальше, если `catch` перехватывает любую
+            StatementKind::Try { body, catches, finally } => {
+                (body_raises(body, raising, error_codes) && !catches_everything(catches, error_codes))
+                    || catches.iter().any(|clause| body_raises(&clause.body, raising, error_codes))
+                    || finally.as_ref().is_some_and(|finally| body_raises(finally, raising, error_codes))
+            }
+            _ => found || child_blocks(stmt).into_iter().any(|block| body_raises(block, raising, error_codes)),
+        }
+    })
+}
+
+/// Есть ли `break`/`continue` без метки внутри тела `try`, которое станет блоком
+/// `'try_N: { ... }` (вложенные циклы не в счёт: переход из них их же и касается)
+fn jumps_out_of_try(body: &[Statement], in_try: bool, raising: &HashSet<String>, error_codes: &[String]) -> bool {
+    body.iter().any(|stmt| match &stmt.kind {
+        StatementKind::Break(None) | StatementKind::Continue(None) => in_try,
+        StatementKind::While { .. } | StatementKind::For { .. } => false,
+        StatementKind::Try { body, catches, finally } => {
+            let body_try = in_try || body_raises(body, raising, error_codes);
+            jumps_out_of_try(body, body_try, raising, error_codes)
+                || catches.iter().any(|clause| jumps_out_of_try(&clause.body, in_try, raising, error_codes))
+                || finally.as_ref().is_some_and(|finally| jumps_out_of_try(finally, in_try, raising, error_codes))
+        }
+        _ => child_blocks(stmt).into_iter().any(|block| jumps_out_of_try(block, in_try, raising, error_codes)),
+    })
+}
+
+/// Оператор, после которого управление дальше не идёт. `try` расходится, если
+/// не завершаются ни его тело, ни ветки `catch`.
+fn diverges(stmt: &Statement) -> bool {
+    match &stmt.kind {
+        StatementKind::Return(_) | StatementKind::Raise(_) | StatementKind::Break(_) | StatementKind::Continue(_) => true,
+        StatementKind::Try { body, catches, .. } => {
+            ends_diverging(body) && catches.iter().all(|clause| ends_diverging(&clause.body))
+        }
+        _ => false,
+    }
+}
+
+/// Последний оператор `body` не передаёт управление дальше
+fn ends_diverging(body: &[Statement]) -> bool {
+    body.last().is_some_and(diverges)
+}
+
+/// Есть ли в `body` выражение (в том числе вложенное), удовлетворяющее `pred`
+fn any_expr(body: &[Statement], pred: &dyn Fn(&Expr) -> bool) -> bool {
+    let mut found = false;
+    for_each_expr(body, &mut |expr| found |= pred(expr));
+    found
+}
+
+/// Переменная, которой принадлежит место `xs[i].name`: `xs`
+fn place_root(mut expr: &Expr) -> Option<&str> {
+    loop {
+        match &expr.kind {
+            ExprKind::Index { target, .. } | ExprKind::Field { target, .. } => expr = target,
+            ExprKind::Identifier(name) => return Some(name),
+            _ => return None,
+        }
+    }
+}
+
+/// Имена переменных, которые изменяются где-либо в `body`: им присваивают
+/// значение или вызывают у них изменяющие методы
+fn assigned_variables(body: &[Statement]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for_each_statement(body, &mut |stmt| {
+        if let StatementKind::Assign { target, .. } = &stmt.kind {
+            names.extend(place_root(target).map(str::to_string));
+        }
+    });
+    for_each_expr(body, &mut |expr| {
+        if let ExprKind::MethodCall { target, name, .. } = &expr.kind {
+            if MUTATING_METHODS.contains(&name.as_str()) {
+                names.extend(place_root(target).map(str::to_string));
+            }
+        }
+    });
+    names
+}
+
+/// Строковый литерал Rust. Строки с кавычками, `\\` или переводами строк
+/// выводятся как raw-литералы `r#"..."#` с достаточным числом `#`,
+/// остальные — как обычные литералы с экранированием.
+fn rust_string_literal(value: &str) -> String {
+    let needs_raw = value.contains(['"', '\\', '\n']);
+    let raw_safe = !value.chars().any(|c| c.is_control() && c != '\n' && c != '\t');
+    if !needs_raw || !raw_safe {
+        return format!("{:?}", value);
+    }
+    
+    // Число `#` должно превышать самую длинную серию `#` после кавычки внутри строки
+    let mut hashes = 0;
+    for (i, _) in value.match_indices('"') {
+        let run = value[i + 1..].chars().take_while(|&c| c == '#').count();
+        hashes = hashes.max(run + 1);
+    }
+    let fence = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", fence, value, fence)
+}
 
 impl Default for CodeGenerator {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    fn generate(source: &str) -> String {
+        match crate::translate_with_modules(source, &["utils".to_string()]) {
+            Ok(code) => code,
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+
+    #[test]
+    fn doc_comments_become_rust_docs() {
+        let code = generate("/// Adds numbers\n///\n/// Twice.\nfunc add(a: int) -> int\n    /// Local\n    auto b = a * 2\n    return b\nend\n");
+        assert!(code.contains("/// Adds numbers\n///\n/// Twice.\nfn add(a: i32) -> i32 {"), "{}", code);
+        // `///` на `let` rustc не принимает как документацию
+        assert!(code.contains("    // Local\n    let b = a * 2;"), "{}", code);
+
+        let code = generate(
+            "/// Upper bound\npublic int LIMIT = 5\npublic int PLAIN = 1\n/// Dropped\npublic txt NAME = \"a\"\n\
+             func f() -> int\n    return LIMIT ** 2 + PLAIN\nend\n",
+        );
+        assert!(code.contains("/// Upper bound\npub const LIMIT: i32 = 5;\npub const PLAIN: i32 = 1;\n"), "{}", code);
+        assert!(!code.contains("NAME") && !code.contains("Dropped"), "{}", code);
+        assert!(code.contains("i32::pow(LIMIT, 2)"), "{}", code);
+        assert_compiles("constants", &code);
+    }
+
+    /// Сгенерированный код без внешних крейтов должен собираться rustc
+    fn assert_compiles(name: &str, code: &str) {
+        let dir = std::env::temp_dir().join(format!("krait_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        std::fs::write(&path, code).unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let output = Command::new(rustc)
+            .args(["--edition", "2021", "--crate-type", "lib", "--out-dir"])
+            .arg(&dir)
+            .arg(&path)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(output.status.success(), "{}\n{}", code, String::from_utf8_lossy(&output.stderr));
+    }
+
+    #[test]
+    fn interpolation_lowers_to_format() {
+        let code = generate("func greet(name: txt, age: int) -> txt\n    return \"Hello {name}, you are {age + 1} \\{literal}\"\nend\n");
+        assert!(code.contains("format!(\"Hello {}, you are {} {{literal}}\", name, age + 1)"), "{}", code);
+        assert_compiles("interpolation", &code);
+
+        let code = generate("func show(xs: list<int>, n: int?) -> txt\n    return \"xs = {xs}, n = {n}, first = {xs[0]}\"\nend\n");
+        assert!(code.contains("format!(\"xs = {:?}, n = {:?}, first = {}\", xs, n, xs[0])"), "{}", code);
+        assert_compiles("interpolation_debug", &code);
+    }
+
+    #[test]
+    fn number_literals_pass_through() {
+        let code = generate("func f()\n    auto a = 0xFF\n    auto b = 1_000i64\n    auto c = 1.5e-3\nend\n");
+        assert!(code.contains("let a = 0xFF;"), "{}", code);
+        assert!(code.contains("let b = 1_000i64;"), "{}", code);
+        assert!(code.contains("let c = 1.5e-3;"), "{}", code);
+    }
+
+    #[test]
+    fn integer_literals_become_floats_in_float_places() {
+        let code = generate(
+            "func half(x: float) -> float\n    return x / 2\nend\nfunc f() -> double\n    double d = 7\n    d += 1\n    \
+             auto h = half(3) * 2\n    return d / -2 + 2 * d\nend\n",
+        );
+        assert!(code.contains("    x / 2.0\n"), "{}", code);
+        assert!(code.contains("let mut d = 7.0;"), "{}", code);
+        assert!(code.contains("d += 1.0;"), "{}", code);
+        assert!(code.contains("let h = half(3.0) * 2.0;"), "{}", code);
+        assert!(code.contains("d / -2.0 + 2.0 * d"), "{}", code);
+        assert_compiles("float_literals", &code);
+    }
+
+    #[test]
+    fn raw_string_literals_get_enough_hashes() {
+        assert_eq!(super::rust_string_literal("plain"), "\"plain\"");
+        assert_eq!(super::rust_string_literal("say \"hi\""), "r#\"say \"hi\"\"#");
+        assert_eq!(super::rust_string_literal("a \"# b"), "r##\"a \"# b\"##");
+    }
+
+    #[test]
+    fn multiline_strings_compile() {
+        let code = generate(
+            "func page() -> txt\n    return \"\"\"\n        <div>\n          \"hi\"\n        </div>\n        \"\"\"\nend\n\
+             func raw() -> txt\n    return r#\"a \"quoted\" \\n\"#\nend\n",
+        );
+        assert!(code.contains("r#\"<div>\n  \"hi\"\n</div>\"#.to_string()"), "{}", code);
+        assert_compiles("multiline_strings", &code);
+    }
+
+    #[test]
+    fn logical_and_compound_operators() {
+        let code = generate(
+            "func f(a: bool, b: bool) -> bool\n    int x = 1\n    x += 2\n    x %= 4\n    return a && !b || not a and b\nend\n",
+        );
+        assert!(code.contains("    x += 2;\n    x %= 4;\n"), "{}", code);
+        assert!(code.contains("a && !b || !a && b"), "{}", code);
+    }
+
+    #[test]
+    fn reassigned_variables_are_mutable() {
+        let code = generate(
+            "func count(limit: int) -> int\n    int i = 0\n    int step = 1\n    while i < limit\n        i = i + step\n    end\n    limit = 0\n    return i\nend\n",
+        );
+        assert!(code.contains("fn count(mut limit: i32) -> i32 {"), "{}", code);
+        assert!(code.contains("let mut i = 0;"), "{}", code);
+        assert!(code.contains("let step = 1;"), "{}", code);
+        assert_compiles("assignment", &code);
+    }
+
+    #[test]
+    fn unary_operators_keep_grouping() {
+        let code = generate(
+            "func f(a: int, b: int, done: bool) -> int\n    bool go = not done\n    auto c = -(a + b) * -a\n    return c\nend\n",
+        );
+        assert!(code.contains("let go = !done;"), "{}", code);
+        assert!(code.contains("let c = -(a + b) * -a;"), "{}", code);
+        assert_compiles("unary", &code);
+    }
+
+    #[test]
+    fn parentheses_follow_precedence_table() {
+        let code = generate(
+            "func f(a: int, b: int, c: int) -> int\n    auto x = (a + b) * c\n    auto y = a - (b - c)\n    auto z = a + b * c\n    \
+             auto p = a ** b ** c\n    auto q = 2.0 ** 0.5\n    return x + y + z + p\nend\n",
+        );
+        assert!(code.contains("let x = (a + b) * c;"), "{}", code);
+        assert!(code.contains("let y = a - (b - c);"), "{}", code);
+        assert!(code.contains("let z = a + b * c;"), "{}", code);
+        assert!(code.contains("let p = i32::pow(a, i32::pow(b, c as u32) as u32);"), "{}", code);
+        assert!(code.contains("let q = f64::powf(2.0, 0.5);"), "{}", code);
+        assert_compiles("precedence", &code);
+    }
+
+    #[test]
+    fn lists_lower_to_vec_with_usize_indexing() {
+        let code = generate(
+            "func f() -> int\n    list<int> xs = [1, 2, 3]\n    auto e = xs[1]\n    return len(xs) + e\nend\n",
+        );
+        assert!(code.contains("завершает программу паникой"), "{}", code);
+        assert!(code.contains("let xs: Vec<i32> = vec![1, 2, 3];"), "{}", code);
+        assert!(code.contains("let e = xs[1];"), "{}", code);
+        assert!(code.contains("xs.len() as i32 + e"), "{}", code);
+        assert_compiles("lists", &code);
+    }
+
+    #[test]
+    fn maps_lower_to_hashmap_operations() {
+        let code = generate(
+            "func f() -> int\n    map<txt, int> m = {\"a\": 1, \"b\": 2}\n    m[\"c\"] = 3\n    auto x = m[\"a\"]\n    \
+             if contains(m, \"b\")\n        return x\n    end\n    return 0\nend\n",
+        );
+        assert!(code.contains("use std::collections::HashMap;"), "{}", code);
+        assert!(!code.contains("serde_json"), "{}", code);
+        assert!(
+            code.contains("let mut m: HashMap<String, i32> = HashMap::from([(\"a\".to_string(), 1), (\"b\".to_string(), 2)]);"),
+            "{}",
+            code
+        );
+        assert!(code.contains("m.insert(\"c\".to_string(), 3);"), "{}", code);
+        assert!(code.contains("let x = m[\"a\"];"), "{}", code);
+        assert!(code.contains("if m.contains_key(\"b\") {"), "{}", code);
+        assert_compiles("maps", &code);
+    }
+
+    #[test]
+    fn contains_method_lowers_like_contains_call() {
+        let code = generate(
+            "func f(m: map<txt, int>, xs: list<int>) -> bool\n    return m.contains(\"a\") && xs.contains(1)\nend\n",
+        );
+        assert!(code.contains("m.contains_key(\"a\") && xs.contains(&1)"), "{}", code);
+        assert_compiles("contains_method", &code);
+    }
+
+    #[test]
+    fn json_map_literal_lowers_to_json_macro() {
+        let code = generate("func g() -> json\n    json j = {\"name\": \"x\", \"n\": 1}\n    return j\nend\n");
+        assert!(code.contains("use serde_json::{json, Value};"), "{}", code);
+        assert!(code.contains("let j: Value = json!({\"name\": \"x\", \"n\": 1});"), "{}", code);
+    }
+
+    #[test]
+    fn method_chains_and_module_calls() {
+        let code = generate(
+            "import utils from utils\nfunc f(text: txt) -> int\n    auto n = text.len()\n    return n + utils.add(1, 2)\nend\n",
+        );
+        assert!(code.contains("mod utils;"), "{}", code);
+        assert!(code.contains("let n = text.len() as i32;"), "{}", code);
+        assert!(code.contains("n + utils::add(1, 2)"), "{}", code);
+
+        // Библиотека, которой нет ни в реестре, ни в проекте, не превращается в `mod`
+        let code = generate("import database from db\nfunc f() -> int\n    return 1\nend\n");
+        assert!(!code.contains("mod db;"), "{}", code);
+
+        let code = generate("func g(xs: list<txt>) -> int\n    return xs[0].trim().len()\nend\n");
+        assert!(code.contains("xs[0].trim().len() as i32"), "{}", code);
+        assert_compiles("method_chain", &code);
+    }
+
+    #[test]
+    fn model_lowers_to_serde_struct_with_defaults() {
+        let code = generate(
+            "model User\n    public txt name\n    int age = 3\nend\nfunc f() -> User\n    return User { name: \"a\" }\nend\n",
+        );
+        assert!(code.contains("use serde::{Deserialize, Serialize};"), "{}", code);
+        assert!(
+            code.contains(
+                "#[derive(Debug, Clone, Serialize, Deserialize)]\n#[serde(default)]\nstruct User {\n    pub name: String,\n    age: i32,\n}"
+            ),
+            "{}",
+            code
+        );
+        assert!(code.contains("            name: Default::default(),\n            age: 3,\n"), "{}", code);
+        assert!(code.contains("User { name: \"a\".to_string(), ..Default::default() }"), "{}", code);
+    }
+
+    /// serde недоступен rustc без Cargo, поэтому модели и перечисления
+    /// проверяются без его derive и атрибутов
+    fn without_serde(code: &str) -> String {
+        code.replace("use serde::{Deserialize, Serialize};", "")
+            .replace(", Serialize, Deserialize", "")
+            .replace("#[serde(default)]\n", "")
+    }
+
+    #[test]
+    fn enums_and_matches_lower_to_rust_enums() {
+        let code = generate(
+            "enum Status\n    Active\n    Banned(reason: txt, days: int)\nend\nfunc f(s: Status) -> int\n    match s\n        \
+             case Status.Active\n            return 1\n        case Status.Banned(reason, d)\n            return d\n    end\nend\n\
+             func g(s: Status) -> int\n    auto n = match s\n        case Active => 0\n        case Banned(_, days) => days\n    \
+             end\n    return n\nend\nfunc h() -> Status\n    return Status.Banned(\"spam\", 3)\nend\n",
+        );
+        assert!(
+            code.contains(
+                "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\nenum Status {\n    Active,\n    Banned { reason: String, days: i32 },\n}"
+            ),
+            "{}",
+            code
+        );
+        assert!(code.contains("Status::Banned { reason, days: d } => {\n            return d;\n        }"), "{}", code);
+        assert!(
+            code.contains("let n = match s.clone() {\n        Status::Active => 0,\n        Status::Banned { reason: _, days } => days,\n    };"),
+            "{}",
+            code
+        );
+        assert!(code.contains("Status::Banned { reason: \"spam\".to_string(), days: 3 }"), "{}", code);
+        assert_compiles("enums", &without_serde(&code));
+    }
+
+    #[test]
+    fn elif_chain_lowers_to_else_if() {
+        let code = generate(
+            "func f(x: int) -> int\n    if x < 0\n        return -1\n    elif x == 0\n        return 0\n    else if x < 10\n        \
+             return 1\n    else\n        return 2\n    end\nend\n",
+        );
+        assert!(
+            code.contains(
+                "    if x < 0 {\n        return -1;\n    } else if x == 0 {\n        return 0;\n    } else if x < 10 {\n        \
+                 return 1;\n    } else {\n        return 2;\n    }\n}"
+            ),
+            "{}",
+            code
+        );
+        assert_compiles("elif", &code);
+    }
+
+    #[test]
+    fn loop_labels_lower_to_rust_labels() {
+        let code = generate(
+            "func f() -> int\n    int n = 0\n    outer: while n < 10\n        for i = 0, 5\n            if i == 3\n                \
+             continue outer\n            end\n            if n > 7\n                break outer\n            end\n            \
+             n += 1\n            break\n        end\n    end\n    return n\nend\n",
+        );
+        assert!(code.contains("'k_outer: while n < 10 {"), "{}", code);
+        assert!(code.contains("continue 'k_outer;"), "{}", code);
+        assert!(code.contains("break 'k_outer;"), "{}", code);
+        assert!(code.contains("            break;\n"), "{}", code);
+        assert_compiles("labels", &code);
+
+        // `'static` и `'loop_N` в Rust заняты
+        let code = generate(
+            "func g(xs: list<int>) -> int\n    int n = 0\n    static: for x in xs\n        loop_1: while n < x\n            \
+             n += 1\n            if n > 5\n                break loop_1\n            end\n            continue static\n        end\n    \
+             end\n    return n\nend\n",
+        );
+        assert!(code.contains("'k_static: for x in xs.clone() {"), "{}", code);
+        assert!(code.contains("continue 'k_static;"), "{}", code);
+        assert!(code.contains("break 'k_loop_1;"), "{}", code);
+        assert_compiles("reserved_labels", &code);
+    }
+
+    #[test]
+    fn for_loops_lower_to_rust_ranges() {
+        let code = generate(
+            "func f(xs: list<int>, m: map<txt, int>) -> int\n    int total = 0\n    for x in xs\n        total += x\n    end\n    \
+             for k, v in m\n        total += v\n    end\n    for i = 0, 10, 2\n        total += i\n    end\n    \
+             for i = 10 to 0, -3\n        total += i\n    end\n    for i = 1 to 3\n        total += i\n    end\n    \
+             return total\nend\n",
+        );
+        assert!(code.contains("for x in xs.clone() {"), "{}", code);
+        assert!(code.contains("for (k, v) in m.clone() {"), "{}", code);
+        assert!(code.contains("for i in (0..10).step_by(2) {"), "{}", code);
+        assert!(code.contains("for i in (0..=10).rev().step_by(3) {"), "{}", code);
+        assert!(code.contains("for i in 1..=3 {"), "{}", code);
+        assert_compiles("for_loops", &code);
+    }
+
+    #[test]
+    fn for_loop_with_runtime_step_picks_direction() {
+        let code = generate(
+            "func f(step: int) -> int\n    int total = 0\n    for i = 10, 0, step\n        total += i\n    end\n    return total\nend\n",
+        );
+        assert!(code.contains("if krait_step < 0 { Box::new((1..=10).rev().step_by((-krait_step) as usize)) }"), "{}", code);
+        assert!(code.contains("else { Box::new((10..0).step_by(krait_step as usize)) }"), "{}", code);
+        assert_compiles("runtime_step", &code);
+    }
+
+    #[test]
+    fn raise_lowers_to_result_with_propagation() {
+        let code = generate(
+            "error_code NotFound = 404\nerror_code BadInput = 400 \"bad input\"\nfunc find(id: int) -> int\n    if id < 0\n        \
+             raise BadInput\n    end\n    if id == 0\n        raise NotFound\n    end\n    return id\nend\n\
+             func twice(id: int) -> int\n    return find(id) * 2\nend\n",
+        );
+        assert!(code.contains("pub enum KraitError {\n    NotFound,\n    BadInput,\n}"), "{}", code);
+        assert!(code.contains("KraitError::NotFound => 404,"), "{}", code);
+        assert!(code.contains("KraitError::NotFound => \"NotFound\","), "{}", code);
+        assert!(code.contains("KraitError::BadInput => \"bad input\","), "{}", code);
+        assert!(code.contains("fn find(id: i32) -> Result<i32, KraitError> {"), "{}", code);
+        assert!(code.contains("return Err(KraitError::BadInput);"), "{}", code);
+        assert!(code.contains("    Ok(id)\n"), "{}", code);
+        assert!(code.contains("fn twice(id: i32) -> Result<i32, KraitError> {\n    Ok(find(id)? * 2)\n}"), "{}", code);
+        assert_compiles("raise", &code);
+    }
+
+    #[test]
+    fn try_returning_from_every_branch() {
+        let code = generate(
+            "error_code NotFound = 404\n\
+             func check(id: int) -> int\n    if id < 0\n        raise NotFound\n    end\n    return id\nend\n\
+             func safe(id: int) -> int\n    try\n        return check(id)\n    catch err\n        return 0\n    end\nend\n\
+             func logged(id: int) -> int\n    try\n        return check(id)\n    catch NotFound\n        return -1\n    finally\n        check(0)\n    end\nend\n",
+        );
+        assert!(code.contains("Ok(()) => unreachable!(),"), "{}", code);
+        assert!(!code.contains("Ok(()) => {}"), "{}", code);
+        assert_compiles("try_returning", &code);
+    }
+
+    #[test]
+    fn try_catching_every_code_has_no_fallthrough_arm() {
+        let code = generate(
+            "error_code NotFound = 404\nfunc check(id: int) -> int\n    if id < 0\n        raise NotFound\n    end\n    \
+             return id\nend\nfunc f(id: int) -> int\n    int total = 0\n    try\n        total = check(id)\n    \
+             catch NotFound e\n        total = -1\n    finally\n        total += 1\n    end\n    return total\nend\n\
+             func twice(id: int) -> int\n    return f(id) * 2\nend\n",
+        );
+        assert!(code.contains("Err(e @ KraitError::NotFound) => {"), "{}", code);
+        assert!(!code.contains("Err(err) => {"), "{}", code);
+        // Ошибки не выходят из `f`: ни `Result`, ни `?` у вызывающих
+        assert!(code.contains("fn f(id: i32) -> i32 {"), "{}", code);
+        assert!(code.contains("fn twice(id: i32) -> i32 {"), "{}", code);
+        assert!(code.contains("    f(id) * 2\n}"), "{}", code);
+        assert_compiles("try_every_code", &code);
+    }
+
+    #[test]
+    fn try_runs_finally_before_leaving_the_loop() {
+        let code = generate(
+            "error_code NotFound = 404\nfunc check(id: int) -> int\n    if id < 0\n        raise NotFound\n    end\n    \
+             return id\nend\nfunc f(ids: list<int>) -> int\n    int total = 0\n    for id in ids\n        try\n            \
+             total += check(id)\n        catch NotFound e\n            continue\n        catch err\n            break\n        \
+             finally\n            total += 1\n        end\n    end\n    return total\nend\n",
+        );
+        assert!(
+            code.contains(
+                "        match 'try_0: {\n            \
+                 total += match check(id) { Ok(value) => value, Err(err) => break 'try_0 Err(err) };\n            \
+                 Ok(())\n        } {\n            Ok(()) => {}\n            \
+                 Err(e @ KraitError::NotFound) => {\n                total += 1;\n                continue;\n            }\n            \
+                 Err(err) => {\n                total += 1;\n                break;\n            }\n        }\n        \
+                 total += 1;\n"
+            ),
+            "{}",
+            code
+        );
+        assert_compiles("try_finally", &code);
+    }
+
+    #[test]
+    fn optionals_lower_to_option() {
+        let code = generate(
+            "func find(id: int) -> int?\n    if id < 0\n        return nil\n    end\n    return id\nend\n\
+             func f(id: int) -> int\n    int? x = find(id)\n    if x != nil\n        return x + 1\n    end\n    \
+             return find(id) ?? 0\nend\n",
+        );
+        assert!(code.contains("fn find(id: i32) -> Option<i32> {\n    if id < 0 {\n        return None;\n    }\n    Some(id)\n}"), "{}", code);
+        assert!(code.contains("let x: Option<i32> = find(id);"), "{}", code);
+        assert!(code.contains("if let Some(x) = x {\n        return x + 1;\n    }"), "{}", code);
+        assert!(code.contains("find(id).unwrap_or(0)"), "{}", code);
+        assert_compiles("optionals", &code);
+
+        // Внутри `!= nil` значение уже не `Option`: `??` ничего не добавляет
+        let code = generate("func f(x: int?) -> int\n    if x != nil\n        return x ?? 0\n    end\n    return 1\nend\n");
+        assert!(code.contains("if let Some(x) = x {\n        return x;\n    }"), "{}", code);
+        assert_compiles("narrowed_coalesce", &code);
+
+        // `!= nil` в составе `and`: остальные части проверяются уже над значением
+        let code = generate(
+            "func f(x: int?, name: txt?) -> int\n    if x != nil and x > 3\n        return x\n    \
+             elif name != nil and x != nil and name == \"a\" or name == \"b\"\n        return 0\n    \
+             elif name != nil and x != nil and (name == \"a\" or x < 0)\n        return x\n    end\n    return 1\nend\n",
+        );
+        assert!(code.contains("if let Some(x) = x.filter(|&x| x > 3) {\n        return x;\n    }"), "{}", code);
+        assert!(
+            code.contains(
+                "} else if let Some((name, x)) = name.clone().zip(x).filter(|(name, x)| \
+                 { let (name, x) = (name.clone(), x.clone()); (name == \"a\".to_string() || x < 0) }) {\n        return x;\n    }"
+            ),
+            "{}",
+            code
+        );
+        assert_compiles("narrowed_conjunction", &code);
+    }
+
+    #[test]
+    fn named_types_lower_to_rust_paths() {
+        let code = generate(
+            "model User\n    txt name\nend\ntype Users = list<User>\ntype Id = int\n\
+             func first(users: Users, id: Id) -> User?\n    User u = users[id]\n    return u\nend\n",
+        );
+        assert!(
+            code.contains("fn first(users: Vec<User>, id: i32) -> Option<User> {\n    let u = users[id as usize].clone();\n    Some(u)\n}"),
+            "{}",
+            code
+        );
+        assert_compiles("named_types", &without_serde(&code));
+    }
+
+    #[test]
+    fn enum_fields_use_their_default_value() {
+        let code = generate(
+            "enum Role\n    Guest\n    Admin\nend\nmodel User\n    txt name\n    Role role = Role.Guest\nend\n\
+             func f() -> User\n    return User { name: \"a\" }\nend\n",
+        );
+        assert!(code.contains("#[derive(Debug, Clone, Serialize, Deserialize)]\n#[serde(default)]\nstruct User {"), "{}", code);
+        assert!(code.contains("            name: Default::default(),\n            role: Role::Guest,\n"), "{}", code);
+        assert_compiles("enum_fields", &without_serde(&code));
+    }
+}
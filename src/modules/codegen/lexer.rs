@@ -1,39 +1,199 @@
 // Лексический анализатор для Krait
+use super::error::LexError;
+use super::span::Span;
+use std::fmt;
+
+/// Допустимые суффиксы типа у числовых литералов
+pub const NUMBER_SUFFIXES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize",
+    "u8", "u16", "u32", "u64", "u128", "usize",
+    "f32", "f64",
+];
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
-    Func, End, Return, If, Else, While, For, Try, Catch, Raise, Route,
-    Json, Auto, ErrorCode, Public, Private, Import, From,
+    Func, End, Return, If, Elif, Else, While, For, In, Break, Continue, Try, Catch, Finally, Raise, Route, Model,
+    Enum, Match, Case,
+    Json, Auto, Nil, ErrorCode, Public, Private, Import, From,
     Int, Float, Double, Txt, Bool,
     Get, Post, Put, Delete,
-    Plus, Minus, Star, Slash, Percent, Equal, EqualEqual, NotEqual,
+    Plus, Minus, Star, StarStar, Slash, Percent, Equal, EqualEqual, NotEqual,
+    PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual,
     Less, Greater, LessEqual, GreaterEqual, And, Or, Not,
     LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
-    Comma, Dot, Colon, Arrow,
+    Comma, Dot, Colon, Arrow, FatArrow, Question, QuestionQuestion,
     Identifier(String), Number(String), String(String), Eof,
+    /// Строка с подстановками `"Hello {name}"`
+    InterpolatedString(Vec<StringPart>),
+    /// Документирующий комментарий `/// текст` (без `///`)
+    DocComment(String),
+    /// Фрагмент, который лексер не смог разобрать (ошибка в `Lexer::errors`)
+    Error,
+}
+
+impl TokenType {
+    /// Исходный текст ключевого слова или символа
+    pub fn lexeme(&self) -> &'static str {
+        match self {
+            TokenType::Func => "func",
+            TokenType::End => "end",
+            TokenType::Return => "return",
+            TokenType::If => "if",
+            TokenType::Elif => "elif",
+            TokenType::Else => "else",
+            TokenType::While => "while",
+            TokenType::For => "for",
+            TokenType::In => "in",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Try => "try",
+            TokenType::Catch => "catch",
+            TokenType::Finally => "finally",
+            TokenType::Raise => "raise",
+            TokenType::Route => "route",
+            TokenType::Model => "model",
+            TokenType::Enum => "enum",
+            TokenType::Match => "match",
+            TokenType::Case => "case",
+            TokenType::Json => "json",
+            TokenType::Auto => "auto",
+            TokenType::Nil => "nil",
+            TokenType::ErrorCode => "error_code",
+            TokenType::Public => "public",
+            TokenType::Private => "private",
+            TokenType::Import => "import",
+            TokenType::From => "from",
+            TokenType::Int => "int",
+            TokenType::Float => "float",
+            TokenType::Double => "double",
+            TokenType::Txt => "txt",
+            TokenType::Bool => "bool",
+            TokenType::Get => "get",
+            TokenType::Post => "post",
+            TokenType::Put => "put",
+            TokenType::Delete => "delete",
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Star => "*",
+            TokenType::StarStar => "**",
+            TokenType::Slash => "/",
+            TokenType::Percent => "%",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::NotEqual => "!=",
+            TokenType::PlusEqual => "+=",
+            TokenType::MinusEqual => "-=",
+            TokenType::StarEqual => "*=",
+            TokenType::SlashEqual => "/=",
+            TokenType::PercentEqual => "%=",
+            TokenType::Less => "<",
+            TokenType::Greater => ">",
+            TokenType::LessEqual => "<=",
+            TokenType::GreaterEqual => ">=",
+            TokenType::And => "and",
+            TokenType::Or => "or",
+            TokenType::Not => "not",
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Colon => ":",
+            TokenType::Arrow => "->",
+            TokenType::FatArrow => "=>",
+            TokenType::Question => "?",
+            TokenType::QuestionQuestion => "??",
+            TokenType::Identifier(_) => "identifier",
+            TokenType::Number(_) => "number",
+            TokenType::String(_) | TokenType::InterpolatedString(_) => "string",
+            TokenType::Eof => "end of file",
+            TokenType::DocComment(_) => "doc comment",
+            TokenType::Error => "invalid token",
+        }
+    }
+}
+
+/// Описание токена для сообщений об ошибках. Токены с пустым значением
+/// (`Identifier(String::new())`) описывают класс токенов в списке ожидаемых.
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenType::Identifier(name) if !name.is_empty() => write!(f, "identifier `{}`", name),
+            TokenType::Number(n) if !n.is_empty() => write!(f, "number `{}`", n),
+            TokenType::String(s) if !s.is_empty() => write!(f, "string {:?}", s),
+            TokenType::Identifier(_) | TokenType::Number(_) | TokenType::String(_)
+            | TokenType::InterpolatedString(_) | TokenType::Eof | TokenType::DocComment(_) | TokenType::Error => {
+                write!(f, "{}", self.lexeme())
+            }
+            _ => write!(f, "`{}`", self.lexeme()),
+        }
+    }
+}
+
+/// Часть строки с подстановками: текст или токены выражения из `{...}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Code(Vec<Token>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub line: usize,
-    pub column: usize,
+    pub span: Span,
 }
 
 pub struct Lexer {
     input: Vec<char>,
+    file_id: usize,
     pos: usize,
+    byte_pos: usize,
     line: usize,
     column: usize,
+    errors: Vec<LexError>,
 }
 
 impl Lexer {
     pub fn new(input: &str) -> Self {
+        Self::with_file_id(input, 0)
+    }
+    
+    /// Лексер для файла с заданным идентификатором (для многофайловых проектов)
+    pub fn with_file_id(input: &str, file_id: usize) -> Self {
         Lexer {
             input: input.chars().collect(),
+            file_id,
             pos: 0,
+            byte_pos: 0,
             line: 1,
             column: 1,
+            errors: Vec::new(),
+        }
+    }
+    
+    /// Ошибки, найденные при последнем вызове `tokenize`
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+    
+    /// Текущая позиция: (байтовое смещение, строка, колонка)
+    fn position(&self) -> (usize, usize, usize) {
+        (self.byte_pos, self.line, self.column)
+    }
+    
+    /// Участок от сохранённой позиции до текущей
+    fn span_from(&self, start: (usize, usize, usize)) -> Span {
+        Span {
+            file_id: self.file_id,
+            start: start.0,
+            end: self.byte_pos,
+            line: start.1,
+            column: start.2,
+            end_line: self.line,
+            end_column: self.column,
         }
     }
     
@@ -56,6 +216,7 @@ impl Lexer {
     fn advance(&mut self) {
         if let Some(ch) = self.current_char() {
             self.pos += 1;
+            self.byte_pos += ch.len_utf8();
             if ch == '\n' {
                 self.line += 1;
                 self.column = 1;
@@ -86,35 +247,349 @@ impl Lexer {
         }
     }
     
-    fn read_string(&mut self, quote: char) -> String {
+    /// `/// текст` — документирующий комментарий (но не `////...`)
+    fn at_doc_comment(&self) -> bool {
+        self.current_char() == Some('/')
+            && self.peek_char(1) == Some('/')
+            && self.peek_char(2) == Some('/')
+            && self.peek_char(3) != Some('/')
+    }
+    
+    fn read_doc_comment(&mut self) -> String {
+        for _ in 0..3 {
+            self.advance();
+        }
+        if self.current_char() == Some(' ') {
+            self.advance();
+        }
         let mut result = String::new();
+        while let Some(ch) = self.current_char() {
+            if ch == '\n' {
+                break;
+            }
+            result.push(ch);
+            self.advance();
+        }
+        result.trim_end().to_string()
+    }
+    
+    /// Пропускает блочный комментарий `/* ... */`, учитывая вложенные
+    fn skip_block_comment(&mut self) {
+        let start = self.position();
+        let mut depth = 0;
+        while let Some(ch) = self.current_char() {
+            if ch == '/' && self.peek_char(1) == Some('*') {
+                depth += 1;
+                self.advance();
+            } else if ch == '*' && self.peek_char(1) == Some('/') {
+                depth -= 1;
+                self.advance();
+                if depth == 0 {
+                    self.advance();
+                    return;
+                }
+            }
+            self.advance();
+        }
+        self.errors.push(LexError::UnterminatedComment { span: self.span_from(start) });
+    }
+    
+    /// Читает строку до закрывающей кавычки. Строка не может переноситься:
+    /// перевод строки или конец файла до кавычки — ошибка. Фрагменты `{expr}`
+    /// превращают строку в `InterpolatedString`, `\{` даёт обычную скобку.
+    fn read_string(&mut self, quote: char) -> TokenType {
+        let start = self.position();
         self.advance();
+        self.read_string_body(start, quote, None)
+    }
+    
+    fn at_triple_quote(&self) -> bool {
+        self.current_char() == Some('"') && self.peek_char(1) == Some('"') && self.peek_char(2) == Some('"')
+    }
+    
+    /// Многострочная строка `"""..."""`. Перевод строки сразу после открывающих
+    /// кавычек и последняя строка из одних пробелов перед закрывающими
+    /// отбрасываются, общий отступ непустых строк срезается.
+    fn read_multiline_string(&mut self) -> TokenType {
+        let start = self.position();
+        for _ in 0..3 {
+            self.advance();
+        }
+        let starts_on_new_line = self.current_char() == Some('\n');
+        if starts_on_new_line {
+            self.advance();
+        }
+        let indent = self.common_indent(starts_on_new_line);
+        if starts_on_new_line {
+            self.skip_indent(indent);
+        }
         
-        while let Some(ch) = self.current_char() {
-            if ch == quote {
+        let mut token = self.read_string_body(start, '"', Some(indent));
+        let last_text = match &mut token {
+            TokenType::String(text) => Some(text),
+            TokenType::InterpolatedString(parts) => match parts.last_mut() {
+                Some(StringPart::Text(text)) => Some(text),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(text) = last_text {
+            if let Some(newline) = text.rfind('\n') {
+                if text[newline + 1..].chars().all(|c| c == ' ' || c == '\t') {
+                    text.truncate(newline);
+                }
+            }
+        }
+        token
+    }
+    
+    /// Наименьший отступ среди непустых строк многострочной строки,
+    /// начиная с текущей позиции и до закрывающих `"""`
+    fn common_indent(&self, at_line_start: bool) -> usize {
+        let mut indent = usize::MAX;
+        let mut at_start = at_line_start;
+        let mut width = 0;
+        let mut i = self.pos;
+        while i < self.input.len() {
+            let ch = self.input[i];
+            if self.input[i..].starts_with(&['"', '"', '"']) {
+                break;
+            }
+            if at_start {
+                if ch == ' ' || ch == '\t' {
+                    width += 1;
+                    i += 1;
+                    continue;
+                }
+                if ch != '\n' {
+                    indent = indent.min(width);
+                }
+                at_start = false;
+            }
+            if ch == '\\' {
+                i += 2;
+                continue;
+            }
+            if ch == '\n' {
+                at_start = true;
+                width = 0;
+            }
+            i += 1;
+        }
+        if indent == usize::MAX { 0 } else { indent }
+    }
+    
+    fn skip_indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            if matches!(self.current_char(), Some(' ' | '\t')) {
                 self.advance();
+            } else {
                 break;
             }
+        }
+    }
+    
+    /// Тело строки после открывающей кавычки. `indent` задан только для
+    /// многострочных строк: тогда строка заканчивается на `"""`, может
+    /// содержать переводы строк, а в начале каждой строки срезается отступ.
+    fn read_string_body(&mut self, start: (usize, usize, usize), quote: char, indent: Option<usize>) -> TokenType {
+        let mut parts = Vec::new();
+        let mut result = String::new();
+        
+        loop {
+            let ch = match self.current_char() {
+                Some('\n') if indent.is_some() => '\n',
+                Some(ch) if ch != '\n' => ch,
+                _ => {
+                    self.errors.push(LexError::UnterminatedString { span: self.span_from(start) });
+                    break;
+                }
+            };
+            if indent.is_some() && self.at_triple_quote() {
+                for _ in 0..3 {
+                    self.advance();
+                }
+                break;
+            }
+            if indent.is_none() && ch == quote {
+                self.advance();
+                break;
+            }
+            if ch == '\n' {
+                result.push(ch);
+                self.advance();
+                self.skip_indent(indent.unwrap_or(0));
+                continue;
+            }
+            if ch == '{' {
+                if !result.is_empty() {
+                    parts.push(StringPart::Text(std::mem::take(&mut result)));
+                }
+                match self.read_interpolation() {
+                    Some(tokens) => parts.push(StringPart::Code(tokens)),
+                    // Незакрытая `{` съела остаток строки, ошибка уже записана
+                    None => return TokenType::Error,
+                }
+                continue;
+            }
             if ch == '\\' {
+                let escape_start = self.position();
                 self.advance();
-                if let Some(escaped) = self.current_char() {
-                    match escaped {
-                        'n' => result.push('\n'),
-                        't' => result.push('\t'),
-                        'r' => result.push('\r'),
-                        '\\' => result.push('\\'),
-                        '"' => result.push('"'),
-                        '\'' => result.push('\''),
-                        _ => result.push(escaped),
+                match self.current_char() {
+                    Some(escaped) if escaped != '\n' => {
+                        match escaped {
+                            'n' => result.push('\n'),
+                            't' => result.push('\t'),
+                            'r' => result.push('\r'),
+                            '0' => result.push('\0'),
+                            '\\' => result.push('\\'),
+                            '"' => result.push('"'),
+                            '\'' => result.push('\''),
+                            '{' => result.push('{'),
+                            '}' => result.push('}'),
+                            _ => {
+                                self.advance();
+                                self.errors.push(LexError::InvalidEscape {
+                                    span: self.span_from(escape_start),
+                                    ch: escaped,
+                                });
+                                result.push(escaped);
+                                continue;
+                            }
+                        }
+                        self.advance();
                     }
-                    self.advance();
+                    _ => {}
                 }
             } else {
                 result.push(ch);
                 self.advance();
             }
         }
-        result
+        
+        if parts.is_empty() {
+            return TokenType::String(result);
+        }
+        if !result.is_empty() {
+            parts.push(StringPart::Text(result));
+        }
+        TokenType::InterpolatedString(parts)
+    }
+    
+    /// Читает `{выражение}` внутри строки и разбивает его на токены отдельным
+    /// лексером, сохраняя позиции относительно исходного файла.
+    fn read_interpolation(&mut self) -> Option<Vec<Token>> {
+        let open = self.position();
+        self.advance();
+        let code_start = self.position();
+        let mut code = String::new();
+        let mut depth = 1;
+        let mut quote: Option<char> = None;
+        
+        while let Some(ch) = self.current_char() {
+            if ch == '\n' {
+                break;
+            }
+            match quote {
+                Some(q) => {
+                    if ch == '\\' {
+                        code.push(ch);
+                        self.advance();
+                        if let Some(next) = self.current_char().filter(|&c| c != '\n') {
+                            code.push(next);
+                            self.advance();
+                        }
+                        continue;
+                    }
+                    if ch == q {
+                        quote = None;
+                    }
+                }
+                None => match ch {
+                    '"' | '\'' => quote = Some(ch),
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                },
+            }
+            code.push(ch);
+            self.advance();
+        }
+        
+        if depth > 0 {
+            self.errors.push(LexError::UnterminatedInterpolation { span: self.span_from(open) });
+            return None;
+        }
+        self.advance();
+        
+        // `{}` без выражения: вместо него парсер увидит уже описанный здесь токен ошибки
+        if code.trim().is_empty() {
+            let span = self.span_from(open);
+            self.errors.push(LexError::EmptyInterpolation { span });
+            return Some(vec![Token { token_type: TokenType::Error, span }]);
+        }
+        
+        let mut inner = Lexer {
+            input: code.chars().collect(),
+            file_id: self.file_id,
+            pos: 0,
+            byte_pos: code_start.0,
+            line: code_start.1,
+            column: code_start.2,
+            errors: Vec::new(),
+        };
+        let tokens = inner.tokenize();
+        self.errors.append(&mut inner.errors);
+        Some(tokens)
+    }
+    
+    /// `r"..."` или `r#"..."#`: без экранирования и подстановок
+    fn at_raw_string(&self) -> bool {
+        if self.current_char() != Some('r') {
+            return false;
+        }
+        let mut offset = 1;
+        while self.peek_char(offset) == Some('#') {
+            offset += 1;
+        }
+        self.peek_char(offset) == Some('"')
+    }
+    
+    fn read_raw_string(&mut self) -> TokenType {
+        let start = self.position();
+        self.advance();
+        let mut hashes = 0;
+        while self.current_char() == Some('#') {
+            hashes += 1;
+            self.advance();
+        }
+        self.advance();
+        
+        let mut result = String::new();
+        loop {
+            match self.current_char() {
+                Some('"') if (1..=hashes).all(|i| self.peek_char(i) == Some('#')) => {
+                    for _ in 0..=hashes {
+                        self.advance();
+                    }
+                    break;
+                }
+                Some(ch) => {
+                    result.push(ch);
+                    self.advance();
+                }
+                None => {
+                    self.errors.push(LexError::UnterminatedString { span: self.span_from(start) });
+                    break;
+                }
+            }
+        }
+        TokenType::String(result)
     }
     
     fn read_identifier(&mut self) -> String {
@@ -130,35 +605,141 @@ impl Lexer {
         result
     }
     
-    fn read_number(&mut self) -> String {
-        let mut result = String::new();
+    /// Читает цифры системы счисления `radix` и разделители `_`,
+    /// возвращает количество прочитанных цифр
+    fn read_digits(&mut self, result: &mut String, radix: u32) -> usize {
+        let mut digits = 0;
         while let Some(ch) = self.current_char() {
-            if ch.is_numeric() || ch == '.' {
+            if ch == '_' {
+                result.push(ch);
+                self.advance();
+            } else if ch.is_digit(radix) {
+                digits += 1;
                 result.push(ch);
                 self.advance();
             } else {
                 break;
             }
         }
-        result
+        digits
     }
     
+    /// Читает число: `123`, `1_000_000`, `1.5`, `1.5e-3`, `0xFF`, `0b1010`,
+    /// `0o17`, с необязательным суффиксом типа (`10i64`, `2.0f32`).
+    /// Вторая дробная часть (`1.2.3`), чужие цифры (`0b102`) и неизвестные
+    /// суффиксы (`12ab`) делают число некорректным.
+    fn read_number(&mut self) -> TokenType {
+        let start = self.position();
+        let mut result = String::new();
+        let mut valid = true;
+        
+        let radix = match (self.current_char(), self.peek_char(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('b')) => 2,
+            (Some('0'), Some('o')) => 8,
+            _ => 10,
+        };
+        
+        if radix == 10 {
+            self.read_digits(&mut result, 10);
+            if self.current_char() == Some('.') && self.peek_char(1).is_some_and(|c| c.is_ascii_digit()) {
+                result.push('.');
+                self.advance();
+                self.read_digits(&mut result, 10);
+            }
+            let exponent = match (self.peek_char(1), self.peek_char(2)) {
+                (Some(c), _) if c.is_ascii_digit() => true,
+                (Some('+' | '-'), Some(c)) => c.is_ascii_digit(),
+                _ => false,
+            };
+            if matches!(self.current_char(), Some('e' | 'E')) && exponent {
+                for _ in 0..2 {
+                    result.push(self.current_char().unwrap());
+                    self.advance();
+                }
+                self.read_digits(&mut result, 10);
+            }
+        } else {
+            for _ in 0..2 {
+                result.push(self.current_char().unwrap());
+                self.advance();
+            }
+            valid = self.read_digits(&mut result, radix) > 0;
+        }
+        
+        if self.current_char().is_some_and(|c| c.is_alphanumeric()) {
+            let mut suffix = String::new();
+            while let Some(ch) = self.current_char() {
+                if ch.is_alphanumeric() {
+                    suffix.push(ch);
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            let float_suffix = suffix.starts_with('f');
+            valid &= NUMBER_SUFFIXES.contains(&suffix.as_str()) && !(float_suffix && radix != 10);
+            result.push_str(&suffix);
+        }
+        
+        if self.current_char() == Some('.') && self.peek_char(1).is_some_and(|c| c.is_ascii_digit()) {
+            valid = false;
+        }
+        if valid {
+            return TokenType::Number(result);
+        }
+        while let Some(ch) = self.current_char() {
+            if ch.is_alphanumeric() || ch == '_' || ch == '.' {
+                result.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.errors.push(LexError::MalformedNumber {
+            span: self.span_from(start),
+            text: result,
+        });
+        TokenType::Error
+    }
+
+    /// Оператор `op` или, если за ним идёт `=`, составное присваивание `assign`
+    fn operator_or_assign(&mut self, op: TokenType, assign: TokenType) -> TokenType {
+        self.advance();
+        if self.current_char() == Some('=') {
+            self.advance();
+            assign
+        } else {
+            op
+        }
+    }
+
     fn keyword_or_identifier(&self, word: &str) -> TokenType {
         match word {
             "func" => TokenType::Func,
             "end" => TokenType::End,
             "return" => TokenType::Return,
             "if" => TokenType::If,
+            "elif" => TokenType::Elif,
             "else" => TokenType::Else,
             "while" => TokenType::While,
             "for" => TokenType::For,
+            "in" => TokenType::In,
+            "break" => TokenType::Break,
+            "continue" => TokenType::Continue,
             "try" => TokenType::Try,
             "catch" => TokenType::Catch,
+            "finally" => TokenType::Finally,
             "raise" => TokenType::Raise,
             "route" => TokenType::Route,
+            "model" => TokenType::Model,
+            "enum" => TokenType::Enum,
+            "match" => TokenType::Match,
+            "case" => TokenType::Case,
             "json" => TokenType::Json,
             "auto" => TokenType::Auto,
             "error_code" => TokenType::ErrorCode,
+            "nil" => TokenType::Nil,
             "public" => TokenType::Public,
             "private" => TokenType::Private,
             "import" => TokenType::Import,
@@ -179,8 +760,11 @@ impl Lexer {
         }
     }
     
+    /// Разбивает вход на токены. Некорректные фрагменты превращаются
+    /// в `TokenType::Error`, а описания ошибок доступны через `errors()`.
     pub fn tokenize(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
+        self.errors.clear();
         
         while self.pos < self.input.len() {
             self.skip_whitespace();
@@ -188,13 +772,24 @@ impl Lexer {
                 break;
             }
             
+            let start = self.position();
+            
+            if self.at_doc_comment() {
+                let text = self.read_doc_comment();
+                tokens.push(Token { token_type: TokenType::DocComment(text), span: self.span_from(start) });
+                continue;
+            }
+            
             if self.current_char() == Some('/') && self.peek_char(1) == Some('/') {
                 self.skip_comment();
                 continue;
             }
             
-            let line = self.line;
-            let column = self.column;
+            if self.current_char() == Some('/') && self.peek_char(1) == Some('*') {
+                self.skip_block_comment();
+                continue;
+            }
+            
             let ch = self.current_char().unwrap();
             
             let token_type = match ch {
@@ -207,24 +802,45 @@ impl Lexer {
                 ',' => { self.advance(); TokenType::Comma }
                 '.' => { self.advance(); TokenType::Dot }
                 ':' => { self.advance(); TokenType::Colon }
-                '+' => { self.advance(); TokenType::Plus }
+                '+' => self.operator_or_assign(TokenType::Plus, TokenType::PlusEqual),
                 '-' => {
-                    self.advance();
-                    if self.current_char() == Some('>') {
+                    if self.peek_char(1) == Some('>') {
+                        self.advance();
                         self.advance();
                         TokenType::Arrow
                     } else {
-                        TokenType::Minus
+                        self.operator_or_assign(TokenType::Minus, TokenType::MinusEqual)
                     }
                 }
-                '*' => { self.advance(); TokenType::Star }
-                '/' => { self.advance(); TokenType::Slash }
-                '%' => { self.advance(); TokenType::Percent }
+                '*' if self.peek_char(1) == Some('*') => {
+                    self.advance();
+                    self.advance();
+                    TokenType::StarStar
+                }
+                // `?` после типа делает его необязательным, `??` — значение по умолчанию
+                '?' if self.peek_char(1) == Some('?') => {
+                    self.advance();
+                    self.advance();
+                    TokenType::QuestionQuestion
+                }
+                '?' => { self.advance(); TokenType::Question }
+                '*' => self.operator_or_assign(TokenType::Star, TokenType::StarEqual),
+                '/' => self.operator_or_assign(TokenType::Slash, TokenType::SlashEqual),
+                '%' => self.operator_or_assign(TokenType::Percent, TokenType::PercentEqual),
+                // `&&` и `||` — синонимы `and` и `or`; одиночные `&` и `|` в Krait не используются
+                '&' | '|' if self.peek_char(1) == Some(ch) => {
+                    self.advance();
+                    self.advance();
+                    if ch == '&' { TokenType::And } else { TokenType::Or }
+                }
                 '=' => {
                     self.advance();
                     if self.current_char() == Some('=') {
                         self.advance();
                         TokenType::EqualEqual
+                    } else if self.current_char() == Some('>') {
+                        self.advance();
+                        TokenType::FatArrow
                     } else {
                         TokenType::Equal
                     }
@@ -256,25 +872,210 @@ impl Lexer {
                         TokenType::Greater
                     }
                 }
-                '"' => TokenType::String(self.read_string('"')),
-                '\'' => TokenType::String(self.read_string('\'')),
+                '"' if self.at_triple_quote() => self.read_multiline_string(),
+                '"' => self.read_string('"'),
+                'r' if self.at_raw_string() => self.read_raw_string(),
+                '\'' => self.read_string('\''),
                 _ if ch.is_alphabetic() || ch == '_' => {
                     let word = self.read_identifier();
                     self.keyword_or_identifier(&word)
                 }
-                _ if ch.is_numeric() => TokenType::Number(self.read_number()),
-                _ => { self.advance(); TokenType::Identifier(ch.to_string()) }
+                _ if ch.is_ascii_digit() => self.read_number(),
+                _ => {
+                    self.advance();
+                    self.errors.push(LexError::UnexpectedChar { span: self.span_from(start), ch });
+                    TokenType::Error
+                }
             };
             
-            tokens.push(Token { token_type, line, column });
+            tokens.push(Token { token_type, span: self.span_from(start) });
         }
         
         tokens.push(Token {
             token_type: TokenType::Eof,
-            line: self.line,
-            column: self.column,
+            span: self.span_from(self.position()),
         });
         
         tokens
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(source: &str) -> (Vec<Token>, Vec<LexError>) {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.tokenize();
+        (tokens, lexer.errors().to_vec())
+    }
+
+    #[test]
+    fn token_spans_record_start_and_end() {
+        let (tokens, errors) = tokenize("func main()\n    total\nend");
+        assert!(errors.is_empty());
+        let func = tokens[0].span;
+        assert_eq!((func.start, func.end, func.line, func.column, func.end_line, func.end_column), (0, 4, 1, 1, 1, 5));
+        let total = tokens[4].span;
+        assert_eq!(tokens[4].token_type, TokenType::Identifier("total".to_string()));
+        assert_eq!((total.start, total.end, total.line, total.column, total.end_column), (16, 21, 2, 5, 10));
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.token_type, TokenType::Eof);
+        assert!(eof.span.is_empty());
+    }
+
+    #[test]
+    fn spans_count_bytes_not_chars() {
+        let (tokens, _) = tokenize("\"привет\" x");
+        assert_eq!(tokens[0].span.len(), "\"привет\"".len());
+        assert_eq!(tokens[1].span.column, 10);
+    }
+
+    #[test]
+    fn file_id_is_carried_into_spans() {
+        let tokens = Lexer::with_file_id("x", 3).tokenize();
+        assert!(tokens.iter().all(|token| token.span.file_id == 3));
+    }
+
+    /// Код, строка, колонка и длина каждой ошибки лексера
+    fn error_codes(source: &str) -> Vec<(&'static str, usize, usize, usize)> {
+        tokenize(source).1.iter().map(|err| (err.code(), err.span().line, err.span().column, err.span().len())).collect()
+    }
+
+    #[test]
+    fn unexpected_characters_become_error_tokens() {
+        let (tokens, errors) = tokenize("x @ y;");
+        assert_eq!(tokens[1].token_type, TokenType::Error);
+        assert_eq!(tokens[3].token_type, TokenType::Error);
+        assert_eq!(errors[0], LexError::UnexpectedChar { span: tokens[1].span, ch: '@' });
+        assert_eq!(error_codes("x @ y;"), [("K0101", 1, 3, 1), ("K0101", 1, 6, 1)]);
+    }
+
+    #[test]
+    fn unterminated_string_spans_to_end_of_line() {
+        assert_eq!(error_codes("x = \"abc\ny = 1"), [("K0102", 1, 5, 4)]);
+        let (tokens, _) = tokenize("x = \"abc\ny = 1");
+        assert_eq!(tokens[3].token_type, TokenType::Identifier("y".to_string()));
+    }
+
+    #[test]
+    fn invalid_escape_points_at_backslash() {
+        assert_eq!(error_codes("\"a\\qb\""), [("K0103", 1, 3, 2)]);
+    }
+
+    #[test]
+    fn malformed_numbers_are_reported_once() {
+        assert_eq!(error_codes("1.2.3 + 1"), [("K0104", 1, 1, 5)]);
+        assert_eq!(error_codes("0xZZ"), [("K0104", 1, 1, 4)]);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let (tokens, errors) = tokenize("a /* outer /* inner */ still comment */ b");
+        assert!(errors.is_empty());
+        let names: Vec<_> = tokens.iter().map(|token| token.token_type.clone()).collect();
+        assert_eq!(
+            names,
+            [TokenType::Identifier("a".to_string()), TokenType::Identifier("b".to_string()), TokenType::Eof]
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comment_points_at_opening() {
+        assert_eq!(error_codes("x\n  /* a /* b */\n"), [("K0105", 2, 3, 13)]);
+    }
+
+    #[test]
+    fn doc_comments_are_tokens() {
+        let (tokens, _) = tokenize("/// Adds numbers\n// plain\nfunc");
+        assert_eq!(tokens[0].token_type, TokenType::DocComment("Adds numbers".to_string()));
+        assert_eq!(tokens[1].token_type, TokenType::Func);
+    }
+
+    #[test]
+    fn interpolation_splits_text_and_code() {
+        let (tokens, errors) = tokenize("\"Hi {name}! \\{x}\"");
+        assert!(errors.is_empty());
+        let TokenType::InterpolatedString(parts) = &tokens[0].token_type else {
+            panic!("expected interpolated string, found {:?}", tokens[0].token_type);
+        };
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], StringPart::Text("Hi ".to_string()));
+        let StringPart::Code(code) = &parts[1] else {
+            panic!("expected code part");
+        };
+        assert_eq!(code[0].token_type, TokenType::Identifier("name".to_string()));
+        // Позиции выражения — относительно исходного файла
+        assert_eq!((code[0].span.line, code[0].span.column), (1, 6));
+        assert_eq!(parts[2], StringPart::Text("! {x}".to_string()));
+    }
+
+    #[test]
+    fn unterminated_interpolation_is_reported() {
+        assert_eq!(error_codes("\"a {b\""), [("K0106", 1, 4, 3)]);
+    }
+
+    #[test]
+    fn empty_interpolation_is_reported() {
+        assert_eq!(error_codes("\"x { } y\""), [("K0107", 1, 4, 3)]);
+    }
+
+    #[test]
+    fn numbers_keep_radix_separators_exponents_and_suffixes() {
+        let numbers: Vec<_> = tokenize("0xFF 0b1010 0o17 1_000_000 1.5e-3 10i64 2.0f32")
+            .0
+            .into_iter()
+            .filter_map(|token| match token.token_type {
+                TokenType::Number(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(numbers, ["0xFF", "0b1010", "0o17", "1_000_000", "1.5e-3", "10i64", "2.0f32"]);
+    }
+
+    #[test]
+    fn bad_suffixes_are_malformed() {
+        assert_eq!(error_codes("10i7"), [("K0104", 1, 1, 4)]);
+        assert_eq!(error_codes("0b102"), [("K0104", 1, 1, 5)]);
+    }
+
+    #[test]
+    fn triple_quoted_strings_strip_common_indentation() {
+        let (tokens, errors) = tokenize("x = \"\"\"\n    <div>\n      \"hi\"\n    </div>\n    \"\"\"");
+        assert!(errors.is_empty());
+        assert_eq!(tokens[2].token_type, TokenType::String("<div>\n  \"hi\"\n</div>".to_string()));
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes() {
+        let (tokens, errors) = tokenize("r\"a\\n\" r#\"say \"hi\"\"#");
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].token_type, TokenType::String("a\\n".to_string()));
+        assert_eq!(tokens[1].token_type, TokenType::String("say \"hi\"".to_string()));
+    }
+
+    #[test]
+    fn symbolic_logic_and_compound_assignment_tokens() {
+        let types: Vec<_> = tokenize("&& || ! != += -= *= /= %=").0.into_iter().map(|token| token.token_type).collect();
+        assert_eq!(
+            types,
+            [
+                TokenType::And,
+                TokenType::Or,
+                TokenType::Not,
+                TokenType::NotEqual,
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn single_ampersand_is_an_error() {
+        assert_eq!(error_codes("a & b | c"), [("K0101", 1, 3, 1), ("K0101", 1, 7, 1)]);
+    }
+}
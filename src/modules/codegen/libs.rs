@@ -44,6 +44,19 @@ impl LibraryRegistry {
             },
         );
         
+        // Коллекции стандартной библиотеки (`map<K, V>`)
+        libs.insert(
+            "collections".to_string(),
+            Library {
+                name: "Collections (std)".to_string(),
+                crate_name: "std".to_string(),
+                imports: vec![
+                    "use std::collections::HashMap;".to_string(),
+                ],
+                features: vec![],
+            },
+        );
+        
         // Database библиотеки
         libs.insert(
             "sqlx".to_string(),
@@ -1,9 +1,15 @@
 // Модульная архитектура кодгенератора
+pub mod check;
+pub mod error;
 pub mod lexer;
 pub mod parser;
+pub mod resolve;
 pub mod gen;
 pub mod libs;
+pub mod span;
 
+pub use error::{LexError, ParseError, TranslateError};
 pub use gen::CodeGenerator;
-pub use parser::Parser;
+pub use parser::{ParseResult, Parser};
 pub use lexer::Lexer;
+pub use span::Span;
@@ -1,41 +1,386 @@
 // Parser для Krait
-use super::lexer::{Token, TokenType};
+use super::error::ParseError;
+use super::lexer::{StringPart, Token, TokenType};
+use super::span::Span;
+use crate::modules::diagnostics::Diagnostic;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Debug, Clone)]
 pub enum DataType {
     Int, Float, Double, Txt, Bool, Auto,
+    /// `list<T>` → `Vec<T>`
+    List(Box<DataType>),
+    /// `map<K, V>` → `HashMap<K, V>`
+    Map(Box<DataType>, Box<DataType>),
+    /// `json` → `serde_json::Value`
+    Json,
+    /// `T?` → `Option<T>`: значение может отсутствовать (`nil`)
+    Optional(Box<DataType>),
+    /// Модель, перечисление или псевдоним по имени: `User`, `utils.User`.
+    /// Псевдонимы раскрывает проход `resolve`, до генерации доходят только модели и перечисления.
+    Named { name: String, span: Span },
 }
 
 impl DataType {
-    pub fn to_rust(&self) -> &str {
+    pub fn to_rust(&self) -> String {
         match self {
-            DataType::Int => "i32",
-            DataType::Float => "f32",
-            DataType::Double => "f64",
-            DataType::Txt => "String",
+            DataType::Int => "i32".to_string(),
+            DataType::Float => "f32".to_string(),
+            DataType::Double => "f64".to_string(),
+            DataType::Txt => "String".to_string(),
+            DataType::Bool => "bool".to_string(),
+            DataType::Auto => "i32".to_string(),
+            DataType::List(item) => format!("Vec<{}>", item.to_rust()),
+            DataType::Map(key, value) => format!("HashMap<{}, {}>", key.to_rust(), value.to_rust()),
+            DataType::Json => "Value".to_string(),
+            DataType::Optional(inner) => format!("Option<{}>", inner.to_rust()),
+            DataType::Named { name, .. } => name.replace('.', "::"),
+        }
+    }
+}
+
+/// Тип так, как он записан в исходнике Krait
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataType::Int => "int",
+            DataType::Float => "float",
+            DataType::Double => "double",
+            DataType::Txt => "txt",
             DataType::Bool => "bool",
-            DataType::Auto => "i32",
+            DataType::Auto => "auto",
+            DataType::Json => "json",
+            DataType::List(item) => return write!(f, "list<{}>", item),
+            DataType::Map(key, value) => return write!(f, "map<{}, {}>", key, value),
+            DataType::Optional(inner) => return write!(f, "{}?", inner),
+            DataType::Named { name, .. } => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Литерал с разобранным значением. Исходная запись числа (`0xFF`, `1_000`,
+/// `2.0f32`) сохраняется в `text`: Rust понимает её без изменений.
+#[derive(Debug, Clone)]
+pub enum Literal {
+    Int { value: u128, text: String, suffix: Option<String> },
+    Float { value: f64, text: String, suffix: Option<String> },
+    Str(String),
+}
+
+impl Literal {
+    /// Разбирает числовой литерал, уже проверенный лексером
+    pub fn from_number(text: &str) -> Literal {
+        let radix = match text.get(..2) {
+            Some("0x") => 16,
+            Some("0b") => 2,
+            Some("0o") => 8,
+            _ => 10,
+        };
+        let body_start = if radix == 10 { 0 } else { 2 };
+        let suffix_start = text
+            .char_indices()
+            .skip(body_start)
+            .find(|&(_, ch)| ch == 'i' || ch == 'u' || (ch == 'f' && radix != 16))
+            .map(|(i, _)| i)
+            .unwrap_or(text.len());
+        let body: String = text[body_start..suffix_start].chars().filter(|&c| c != '_').collect();
+        let suffix = Some(&text[suffix_start..]).filter(|s| !s.is_empty()).map(str::to_string);
+        
+        let is_float = suffix.as_deref().is_some_and(|s| s.starts_with('f'))
+            || (radix == 10 && body.contains(['.', 'e', 'E']));
+        if is_float {
+            Literal::Float {
+                value: body.parse().unwrap_or(f64::INFINITY),
+                text: text.to_string(),
+                suffix,
+            }
+        } else {
+            Literal::Int {
+                value: u128::from_str_radix(&body, radix).unwrap_or(u128::MAX),
+                text: text.to_string(),
+                suffix,
+            }
+        }
+    }
+}
+
+/// Ассоциативность бинарного оператора
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+    /// `a < b < c` запрещено: цепочку сравнений нужно расписать через `and`
+    None,
+}
+
+/// Бинарный оператор Krait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Or, And,
+    Eq, Ne, Lt, Gt, Le, Ge,
+    /// `a ?? b`: значение `a`, а если это `nil` — `b`
+    Coalesce,
+    Add, Sub,
+    Mul, Div, Rem,
+    Pow,
+}
+
+/// Приоритет префиксных операторов: сильнее `*`, но слабее `**` (`-2 ** 2` — это `-(2 ** 2)`)
+pub const UNARY_PRECEDENCE: u8 = 7;
+
+/// Приоритет неделимых выражений: литералов, имён, вызовов
+pub const ATOM_PRECEDENCE: u8 = u8::MAX;
+
+impl BinOp {
+    /// Таблица операторов: токен → (оператор, приоритет, ассоциативность).
+    /// По ней разбирает выражения парсер и расставляет скобки кодогенератор.
+    const TABLE: &'static [(BinOp, u8, Assoc)] = &[
+        (BinOp::Or, 1, Assoc::Left),
+        (BinOp::And, 2, Assoc::Left),
+        (BinOp::Eq, 3, Assoc::None),
+        (BinOp::Ne, 3, Assoc::None),
+        (BinOp::Lt, 3, Assoc::None),
+        (BinOp::Gt, 3, Assoc::None),
+        (BinOp::Le, 3, Assoc::None),
+        (BinOp::Ge, 3, Assoc::None),
+        (BinOp::Coalesce, 4, Assoc::Right),
+        (BinOp::Add, 5, Assoc::Left),
+        (BinOp::Sub, 5, Assoc::Left),
+        (BinOp::Mul, 6, Assoc::Left),
+        (BinOp::Div, 6, Assoc::Left),
+        (BinOp::Rem, 6, Assoc::Left),
+        (BinOp::Pow, 8, Assoc::Right),
+    ];
+    
+    pub fn from_token(token: &TokenType) -> Option<BinOp> {
+        let op = match token {
+            TokenType::Or => BinOp::Or,
+            TokenType::And => BinOp::And,
+            TokenType::EqualEqual => BinOp::Eq,
+            TokenType::NotEqual => BinOp::Ne,
+            TokenType::Less => BinOp::Lt,
+            TokenType::Greater => BinOp::Gt,
+            TokenType::LessEqual => BinOp::Le,
+            TokenType::GreaterEqual => BinOp::Ge,
+            TokenType::QuestionQuestion => BinOp::Coalesce,
+            TokenType::Plus => BinOp::Add,
+            TokenType::Minus => BinOp::Sub,
+            TokenType::Star => BinOp::Mul,
+            TokenType::Slash => BinOp::Div,
+            TokenType::Percent => BinOp::Rem,
+            TokenType::StarStar => BinOp::Pow,
+            _ => return None,
+        };
+        Some(op)
+    }
+    
+    fn entry(self) -> (u8, Assoc) {
+        Self::TABLE
+            .iter()
+            .find(|(op, _, _)| *op == self)
+            .map(|&(_, prec, assoc)| (prec, assoc))
+            .expect("каждый оператор есть в таблице")
+    }
+    
+    pub fn precedence(self) -> u8 {
+        self.entry().0
+    }
+    
+    pub fn assoc(self) -> Assoc {
+        self.entry().1
+    }
+    
+    /// Оператор Rust; `**` своего оператора в Rust не имеет и генерируется вызовом `pow`,
+    /// `??` — вызовом `unwrap_or`
+    pub fn to_rust(self) -> &'static str {
+        match self {
+            BinOp::Or => "||",
+            BinOp::And => "&&",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Gt => ">",
+            BinOp::Le => "<=",
+            BinOp::Ge => ">=",
+            BinOp::Coalesce => "??",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Pow => "**",
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum Expr {
-    Literal(String),
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Literal(Literal),
     Identifier(String),
-    BinaryOp { left: Box<Expr>, op: String, right: Box<Expr> },
+    BinaryOp { left: Box<Expr>, op: BinOp, right: Box<Expr> },
+    /// Префиксный оператор: `-x`, `!x` / `not x`
+    Unary { op: UnaryOp, operand: Box<Expr> },
     FunctionCall { name: String, args: Vec<Expr> },
+    /// Строка с подстановками `"Hello {name}"`
+    Interpolated(Vec<InterpolationPart>),
+    /// Литерал списка `[1, 2, 3]`
+    List(Vec<Expr>),
+    /// Литерал словаря `{"key": value}`
+    Map(Vec<(Expr, Expr)>),
+    /// Обращение по индексу `xs[i]`
+    Index { target: Box<Expr>, index: Box<Expr> },
+    /// Поле `user.name`
+    Field { target: Box<Expr>, name: String },
+    /// Вызов метода `text.len()` или функции импортированного модуля `utils.add(1, 2)`
+    MethodCall { target: Box<Expr>, name: String, args: Vec<Expr> },
+    /// Создание модели `User { name: "a", age: 3 }`; пропущенные поля берутся по умолчанию
+    Construct { model: String, fields: Vec<(String, Expr)> },
+    /// `match` в позиции выражения: у всех веток форма `case образец => значение`
+    Match { scrutinee: Box<Expr>, arms: Vec<MatchArm> },
+    /// Отсутствующее значение необязательного типа
+    Nil,
+    /// Некорректный токен на месте выражения (ошибка уже выдана лексером)
+    Error,
+}
+
+/// Префиксный оператор Krait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `!x` / `not x`
+    Not,
+}
+
+impl UnaryOp {
+    pub fn to_rust(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InterpolationPart {
+    Text(String),
+    Expr(Expr),
+}
+
+/// Образец ветки `case`
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_` — любое значение
+    Wildcard,
+    /// Число, строка, `true` или `false`
+    Literal(Expr),
+    /// Вариант перечисления `Status.Banned(reason, _)`; имя перечисления можно опустить
+    Variant { enum_name: Option<String>, variant: String, bindings: Vec<String> },
+}
+
+/// Тело ветки: значение после `=>` или блок операторов до следующего `case`
+#[derive(Debug, Clone)]
+pub enum ArmBody {
+    Value(Expr),
+    Block(Vec<Statement>),
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: ArmBody,
+    /// Участок образца
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// Приоритет выражения по таблице операторов
+    pub fn precedence(&self) -> u8 {
+        match &self.kind {
+            ExprKind::BinaryOp { op, .. } => op.precedence(),
+            ExprKind::Unary { .. } => UNARY_PRECEDENCE,
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+    
+    /// Может ли выражение стоять слева от присваивания
+    pub fn is_place(&self) -> bool {
+        match &self.kind {
+            ExprKind::Identifier(_) => true,
+            ExprKind::Index { target, .. } | ExprKind::Field { target, .. } => target.is_place(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
-pub enum Statement {
-    VarDecl { name: String, var_type: DataType, value: Option<Expr>, is_public: bool },
+pub enum StatementKind {
+    VarDecl { name: String, var_type: DataType, value: Option<Expr>, is_public: bool, doc: Vec<String> },
     Return(Option<Expr>),
-    If { condition: Expr, body: Vec<Statement>, else_body: Option<Vec<Statement>> },
-    While { condition: Expr, body: Vec<Statement> },
-    For { var: String, start: Expr, end: Expr, body: Vec<Statement> },
+    /// `if ... elif ... else ... end`: условия веток по порядку и необязательный `else`
+    If { branches: Vec<(Expr, Vec<Statement>)>, else_body: Option<Vec<Statement>> },
+    /// Цикл с необязательной меткой `outer: while ...`
+    While { condition: Expr, body: Vec<Statement>, label: Option<String> },
+    For { var: String, iter: ForIter, body: Vec<Statement>, label: Option<String> },
+    /// `break` / `break outer`
+    Break(Option<String>),
+    /// `continue` / `continue outer`
+    Continue(Option<String>),
     ExprStmt(Expr),
-    Try { body: Vec<Statement>, catch_body: Vec<Statement> },
+    /// Присваивание `x = v` или составное `x += v` (`op` — оператор без `=`)
+    Assign { target: Expr, op: Option<String>, value: Expr },
+    /// `try ... catch ... finally ... end`: ветки `catch` проверяются по порядку
+    Try { body: Vec<Statement>, catches: Vec<CatchClause>, finally: Option<Vec<Statement>> },
+    /// `match x ... end`: ветки могут быть и блоками, и значениями
+    Match { scrutinee: Expr, arms: Vec<MatchArm> },
+    /// `raise NotFound`: имя кода из `error_code`
+    Raise(String),
+    /// Оператор, который не удалось разобрать (ошибка уже в диагностиках)
+    Error,
+}
+
+/// Ветка `catch`: `catch err` ловит любую ошибку, `catch NotFound err` и `catch NotFound` —
+/// только код `NotFound`; `_` вместо имени переменной ошибку не связывает
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    pub code: Option<String>,
+    pub var: Option<String>,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+/// Что перебирает цикл `for`
+#[derive(Debug, Clone)]
+pub enum ForIter {
+    /// `for i = start, end` (без `end`) или `for i = start to end` (включая `end`);
+    /// необязательный шаг — третьим: `for i = 10, 0, -2`
+    Range { start: Box<Expr>, end: Box<Expr>, step: Option<Box<Expr>>, inclusive: bool },
+    /// `for item in xs`; для словаря `for key, value in m` — `value` получает значение
+    Each { value: Option<String>, collection: Expr },
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Statement { kind, span }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +390,8 @@ pub struct FunctionDef {
     pub return_type: DataType,
     pub body: Vec<Statement>,
     pub is_public: bool,
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -52,24 +399,161 @@ pub struct RouteDef {
     pub path: String,
     pub method: String,
     pub body: Vec<Statement>,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Поле модели: `public txt name = "guest"`
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub name: String,
+    pub field_type: DataType,
+    pub default: Option<Expr>,
+    pub is_public: bool,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Модель данных `model User ... end` — структура для тел запросов и ответов
+#[derive(Debug, Clone)]
+pub struct ModelDef {
+    pub name: String,
+    pub fields: Vec<FieldDef>,
+    pub is_public: bool,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Вариант перечисления; поля записываются как параметры: `Banned(reason: txt, days: int)`
+#[derive(Debug, Clone)]
+pub struct VariantDef {
+    pub name: String,
+    pub fields: Vec<(String, DataType)>,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Перечисление `enum Status ... end`
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec<VariantDef>,
+    pub is_public: bool,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+impl EnumDef {
+    pub fn variant(&self, name: &str) -> Option<&VariantDef> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+}
+
+/// Псевдоним типа `type Users = list<User>`
+#[derive(Debug, Clone)]
+pub struct TypeAliasDef {
+    pub name: String,
+    pub target: DataType,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Код ошибки `error_code NotFound = 404 "user not found"`
+#[derive(Debug, Clone)]
+pub struct ErrorCodeDef {
+    pub name: String,
+    /// HTTP-статус ответа, в который превращается ошибка (400–599)
+    pub status: u16,
+    /// Текст ошибки; без него используется имя кода
+    pub message: Option<String>,
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum TopLevel {
     Function(FunctionDef),
     Route(RouteDef),
-    Statement(Statement),
-    Import { module: String, from: String },
+    Model(ModelDef),
+    Enum(EnumDef),
+    ErrorCode(ErrorCodeDef),
+    TypeAlias(TypeAliasDef),
+    Statement(Box<Statement>),
+    Import { module: String, from: String, span: Span },
+    /// Конструкция, которую не удалось разобрать (ошибка уже в диагностиках)
+    Error(Span),
+}
+
+impl TopLevel {
+    pub fn span(&self) -> Span {
+        match self {
+            TopLevel::Function(func) => func.span,
+            TopLevel::Route(route) => route.span,
+            TopLevel::Model(model) => model.span,
+            TopLevel::Enum(enum_def) => enum_def.span,
+            TopLevel::ErrorCode(error_code) => error_code.span,
+            TopLevel::TypeAlias(alias) => alias.span,
+            TopLevel::Statement(stmt) => stmt.span,
+            TopLevel::Import { span, .. } | TopLevel::Error(span) => *span,
+        }
+    }
+}
+
+/// Результат разбора: AST (частичный, если были ошибки) и найденные ошибки
+#[derive(Debug)]
+pub struct ParseResult {
+    pub items: Vec<TopLevel>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    diagnostics: Vec<Diagnostic>,
+    /// Документирующие комментарии по индексу токена, перед которым они стоят
+    docs: HashMap<usize, Vec<String>>,
+    /// Метки циклов, внутри которых идёт разбор (`None` — цикл без метки)
+    loops: Vec<Option<String>>,
+    /// Имена из `error_code` во всём файле: по ним `catch Имя` отличает код от переменной
+    error_codes: HashSet<String>,
 }
 
 impl Parser {
+    /// Документирующие комментарии убираются из потока токенов, чтобы не мешать
+    /// разбору, и привязываются к следующему за ними токену.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+        let mut filtered = Vec::with_capacity(tokens.len());
+        let mut docs = HashMap::new();
+        let mut pending = Vec::new();
+        for token in tokens {
+            if let TokenType::DocComment(text) = token.token_type {
+                pending.push(text);
+            } else {
+                if !pending.is_empty() {
+                    docs.insert(filtered.len(), std::mem::take(&mut pending));
+                }
+                filtered.push(token);
+            }
+        }
+        let error_codes = filtered
+            .windows(2)
+            .filter_map(|pair| match (&pair[0].token_type, &pair[1].token_type) {
+                (TokenType::ErrorCode, TokenType::Identifier(name)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        Parser { tokens: filtered, pos: 0, diagnostics: Vec::new(), docs, loops: Vec::new(), error_codes }
+    }
+    
+    /// Документирующие комментарии перед текущим токеном
+    fn doc_comments(&self) -> Vec<String> {
+        self.docs.get(&self.pos).cloned().unwrap_or_default()
     }
     
     fn current_token(&self) -> &Token {
@@ -80,49 +564,190 @@ impl Parser {
         &self.tokens[(self.pos + offset).min(self.tokens.len() - 1)]
     }
     
+    fn current_span(&self) -> Span {
+        self.current_token().span
+    }
+    
+    /// Участок последнего поглощённого токена
+    fn previous_span(&self) -> Span {
+        if self.pos == 0 {
+            self.current_span()
+        } else {
+            self.tokens[(self.pos - 1).min(self.tokens.len() - 1)].span
+        }
+    }
+    
+    /// Участок узла, начавшегося в `start` и закончившегося на предыдущем токене
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous_span())
+    }
+    
+    /// Ожидалось имя (функции, параметра, ...), а найден другой токен
+    fn expected_name(&self, what: &'static str) -> ParseError {
+        ParseError::ExpectedName {
+            span: self.current_span(),
+            what,
+            found: self.current_token().token_type.clone(),
+        }
+    }
+    
     fn advance(&mut self) {
         if self.pos < self.tokens.len() {
             self.pos += 1;
         }
     }
     
-    fn expect(&mut self, expected: TokenType) -> Result<(), String> {
+    fn expect(&mut self, expected: TokenType) -> Result<(), ParseError> {
         if std::mem::discriminant(&self.current_token().token_type)
             == std::mem::discriminant(&expected)
         {
             self.advance();
             Ok(())
         } else {
-            Err(format!("Expected {:?}, found {:?}", expected, self.current_token().token_type))
+            Err(ParseError::UnexpectedToken {
+                span: self.current_span(),
+                expected: vec![expected],
+                found: self.current_token().token_type.clone(),
+            })
         }
     }
     
-    pub fn parse(&mut self) -> Result<Vec<TopLevel>, String> {
+    fn report(&mut self, err: ParseError) {
+        // Некорректный токен уже описан лексером, вторая ошибка на нём — лишний шум
+        if matches!(err.found(), TokenType::Error) {
+            return;
+        }
+        self.diagnostics.push(err.into());
+    }
+    
+    /// Разбирает весь файл. Ошибки не прерывают разбор: парсер записывает
+    /// диагностику, пропускает токены до точки синхронизации и продолжает.
+    pub fn parse(&mut self) -> ParseResult {
         let mut items = Vec::new();
         while !matches!(self.current_token().token_type, TokenType::Eof) {
-            items.push(self.parse_top_level()?);
+            let start = self.current_span();
+            let before = self.pos;
+            let opens_block = self.opens_block();
+            match self.parse_top_level() {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.report(err);
+                    self.recover(opens_block, before);
+                    // Лишний `end` на верхнем уровне не закрывает ни одного блока
+                    if matches!(self.current_token().token_type, TokenType::End) || self.pos == before {
+                        self.advance();
+                    }
+                    items.push(TopLevel::Error(self.span_from(start)));
+                }
+            }
+        }
+        ParseResult {
+            items,
+            diagnostics: std::mem::take(&mut self.diagnostics),
+        }
+    }
+    
+    /// Начинает ли текущий токен конструкцию, закрываемую своим `end`
+    fn opens_block(&self) -> bool {
+        matches!(
+            self.current_token().token_type,
+            TokenType::Func | TokenType::Route | TokenType::Model | TokenType::Enum | TokenType::Public
+                | TokenType::Private | TokenType::If | TokenType::While | TokenType::For | TokenType::Try
+                | TokenType::Match
+        ) || self.at_loop_label()
+    }
+    
+    /// Метка цикла `outer:` перед `while` или `for`
+    fn at_loop_label(&self) -> bool {
+        matches!(self.current_token().token_type, TokenType::Identifier(_))
+            && matches!(self.peek_token(1).token_type, TokenType::Colon)
+            && matches!(self.peek_token(2).token_type, TokenType::While | TokenType::For)
+    }
+    
+    /// Токены, с которых начинаются конструкции верхнего уровня
+    fn at_top_level_start(&self) -> bool {
+        matches!(
+            self.current_token().token_type,
+            TokenType::Func | TokenType::Route | TokenType::Model | TokenType::Enum | TokenType::ErrorCode
+                | TokenType::Import | TokenType::Public | TokenType::Private | TokenType::Eof
+        ) || self.at_type_alias()
+    }
+    
+    /// `type Имя =`; `type` — контекстное слово, как `to` в `for`
+    fn at_type_alias(&self) -> bool {
+        matches!(&self.current_token().token_type, TokenType::Identifier(word) if word == "type")
+            && matches!(self.peek_token(1).token_type, TokenType::Identifier(_))
+            && matches!(self.peek_token(2).token_type, TokenType::Equal)
+    }
+    
+    /// Panic-mode восстановление после ошибки. Если ошибочная конструкция
+    /// открывала блок, пропускаем его целиком до парного `end`, иначе —
+    /// остаток строки. В обоих случаях останавливаемся на `func`, `route`,
+    /// `import`: они не могут стоять внутри блока. `public`/`private` внутри
+    /// пропускаемого блока остаются его частью, иначе потеряется парный `end`.
+    /// `start` — позиция первого токена ошибочной конструкции.
+    fn recover(&mut self, opened_block: bool, start: usize) {
+        if opened_block {
+            let mut depth = 1;
+            while !self.at_top_level_start()
+                || matches!(self.current_token().token_type, TokenType::Public | TokenType::Private)
+            {
+                match self.current_token().token_type {
+                    TokenType::If | TokenType::While | TokenType::For | TokenType::Try | TokenType::Match => {
+                        depth += 1
+                    }
+                    TokenType::End => {
+                        depth -= 1;
+                        if depth == 0 {
+                            self.advance();
+                            return;
+                        }
+                    }
+                    _ => {}
+                }
+                self.advance();
+            }
+        } else {
+            let line = self.current_span().line;
+            // Оборванное выражение (`(1 +`) закончилось на прошлой строке, а ошибочный
+            // токен открывает следующий блок: его `end` нельзя потерять
+            if self.pos > start && self.previous_span().end_line < line && self.opens_block() {
+                return;
+            }
+            while !self.at_top_level_start()
+                && !matches!(self.current_token().token_type, TokenType::End)
+                && self.current_span().line == line
+            {
+                self.advance();
+            }
         }
-        Ok(items)
     }
     
-    fn parse_top_level(&mut self) -> Result<TopLevel, String> {
+    fn parse_top_level(&mut self) -> Result<TopLevel, ParseError> {
+        let start = self.current_span();
+        let doc = self.doc_comments();
         match &self.current_token().token_type {
             TokenType::Import => self.parse_import(),
-            TokenType::Route => self.parse_route(),
+            TokenType::Route => self.parse_route(doc),
             TokenType::Public => {
                 self.advance();
-                self.parse_function_or_var(true)
+                self.parse_function_or_var(true, start, doc)
             }
             TokenType::Private => {
                 self.advance();
-                self.parse_function_or_var(false)
+                self.parse_function_or_var(false, start, doc)
             }
-            TokenType::Func => self.parse_function_or_var(false),
-            _ => self.parse_statement().map(TopLevel::Statement),
+            TokenType::Func => self.parse_function_or_var(false, start, doc),
+            TokenType::Model => self.parse_model(false, start, doc),
+            TokenType::Enum => self.parse_enum(false, start, doc),
+            TokenType::ErrorCode => self.parse_error_code(start, doc),
+            _ if self.at_type_alias() => self.parse_type_alias(start, doc),
+            _ => self.parse_statement().map(|stmt| TopLevel::Statement(Box::new(stmt))),
         }
     }
     
-    fn parse_import(&mut self) -> Result<TopLevel, String> {
+    fn parse_import(&mut self) -> Result<TopLevel, ParseError> {
+        let start = self.current_span();
         self.expect(TokenType::Import)?;
         let module = match &self.current_token().token_type {
             TokenType::Identifier(name) => {
@@ -130,7 +755,7 @@ impl Parser {
                 self.advance();
                 m
             }
-            _ => return Err("Expected module name".to_string()),
+            _ => return Err(self.expected_name("module name")),
         };
         self.expect(TokenType::From)?;
         let from = match &self.current_token().token_type {
@@ -139,20 +764,38 @@ impl Parser {
                 self.advance();
                 f
             }
-            _ => return Err("Expected from module".to_string()),
+            _ => return Err(self.expected_name("library name")),
         };
-        Ok(TopLevel::Import { module, from })
+        Ok(TopLevel::Import { module, from, span: self.span_from(start) })
     }
     
-    fn parse_function_or_var(&mut self, is_public: bool) -> Result<TopLevel, String> {
+    fn parse_function_or_var(
+        &mut self,
+        is_public: bool,
+        start: Span,
+        doc: Vec<String>,
+    ) -> Result<TopLevel, ParseError> {
         if matches!(self.current_token().token_type, TokenType::Func) {
-            self.parse_function(is_public)
-        } else {
-            self.parse_statement().map(TopLevel::Statement)
+            return self.parse_function(is_public, start, doc);
         }
+        if matches!(self.current_token().token_type, TokenType::Model) {
+            return self.parse_model(is_public, start, doc);
+        }
+        if matches!(self.current_token().token_type, TokenType::Enum) {
+            return self.parse_enum(is_public, start, doc);
+        }
+        let mut stmt = self.parse_statement()?;
+        if let StatementKind::VarDecl { is_public: var_public, doc: var_doc, .. } = &mut stmt.kind {
+            *var_public = is_public;
+            if var_doc.is_empty() {
+                *var_doc = doc;
+            }
+        }
+        stmt.span = start.to(stmt.span);
+        Ok(TopLevel::Statement(Box::new(stmt)))
     }
     
-    fn parse_function(&mut self, is_public: bool) -> Result<TopLevel, String> {
+    fn parse_function(&mut self, is_public: bool, start: Span, doc: Vec<String>) -> Result<TopLevel, ParseError> {
         self.expect(TokenType::Func)?;
         let name = match &self.current_token().token_type {
             TokenType::Identifier(n) => {
@@ -160,7 +803,7 @@ impl Parser {
                 self.advance();
                 name
             }
-            _ => return Err("Expected function name".to_string()),
+            _ => return Err(self.expected_name("function name")),
         };
         self.expect(TokenType::LeftParen)?;
         let mut params = Vec::new();
@@ -171,7 +814,7 @@ impl Parser {
                     self.advance();
                     pn
                 }
-                _ => return Err("Expected parameter name".to_string()),
+                _ => return Err(self.expected_name("parameter name")),
             };
             self.expect(TokenType::Colon)?;
             let param_type = self.parse_type()?;
@@ -187,142 +830,569 @@ impl Parser {
         } else {
             DataType::Auto
         };
-        let body = self.parse_block()?;
+        let body = self.parse_block();
         Ok(TopLevel::Function(FunctionDef {
             name,
             params,
             return_type,
             body,
             is_public,
+            doc,
+            span: self.span_from(start),
         }))
     }
     
-    fn parse_route(&mut self) -> Result<TopLevel, String> {
-        self.expect(TokenType::Route)?;
-        let path = match &self.current_token().token_type {
-            TokenType::String(p) => {
-                let path = p.clone();
+    fn parse_model(&mut self, is_public: bool, start: Span, doc: Vec<String>) -> Result<TopLevel, ParseError> {
+        self.expect(TokenType::Model)?;
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let name = n.clone();
                 self.advance();
-                path
+                name
             }
-            _ => return Err("Expected route path".to_string()),
-        };
-        let method = match &self.current_token().token_type {
-            TokenType::Get => { self.advance(); "GET".to_string() }
-            TokenType::Post => { self.advance(); "POST".to_string() }
-            TokenType::Put => { self.advance(); "PUT".to_string() }
-            TokenType::Delete => { self.advance(); "DELETE".to_string() }
-            _ => return Err("Expected HTTP method".to_string()),
+            _ => return Err(self.expected_name("model name")),
         };
-        let body = self.parse_block()?;
-        Ok(TopLevel::Route(RouteDef { path, method, body }))
-    }
-    
-    fn parse_type(&mut self) -> Result<DataType, String> {
-        match &self.current_token().token_type {
-            TokenType::Int => { self.advance(); Ok(DataType::Int) }
-            TokenType::Float => { self.advance(); Ok(DataType::Float) }
-            TokenType::Double => { self.advance(); Ok(DataType::Double) }
-            TokenType::Txt => { self.advance(); Ok(DataType::Txt) }
-            TokenType::Bool => { self.advance(); Ok(DataType::Bool) }
-            TokenType::Auto => { self.advance(); Ok(DataType::Auto) }
-            _ => Err("Expected data type".to_string()),
-        }
-    }
-    
-    fn parse_block(&mut self) -> Result<Vec<Statement>, String> {
-        let mut statements = Vec::new();
-        while !matches!(self.current_token().token_type, TokenType::End | TokenType::Eof) {
-            statements.push(self.parse_statement()?);
-        }
-        if matches!(self.current_token().token_type, TokenType::End) {
-            self.advance();
-        }
-        Ok(statements)
-    }
-    
-    fn parse_statement(&mut self) -> Result<Statement, String> {
-        match &self.current_token().token_type {
-            TokenType::Return => self.parse_return(),
-            TokenType::If => self.parse_if(),
-            TokenType::While => self.parse_while(),
-            TokenType::For => self.parse_for(),
-            TokenType::Try => self.parse_try(),
-            _ => {
-                if self.is_var_decl() {
-                    self.parse_var_decl()
-                } else {
-                    let expr = self.parse_expr()?;
-                    Ok(Statement::ExprStmt(expr))
+        let mut fields = Vec::new();
+        while !matches!(self.current_token().token_type, TokenType::End) && !self.at_top_level_start_in_model() {
+            let before = self.pos;
+            match self.parse_field() {
+                Ok(field) => fields.push(field),
+                Err(err) => {
+                    self.report(err);
+                    self.recover(false, before);
                 }
             }
         }
+        self.expect(TokenType::End)?;
+        Ok(TopLevel::Model(ModelDef { name, fields, is_public, doc, span: self.span_from(start) }))
     }
     
-    fn is_var_decl(&self) -> bool {
-        matches!(self.current_token().token_type, TokenType::Auto)
-            || (matches!(self.current_token().token_type, TokenType::Identifier(_))
-                && matches!(self.peek_token(1).token_type, TokenType::Identifier(_)))
+    /// Внутри модели `public`/`private` задают видимость поля, а не начинают новое объявление
+    fn at_top_level_start_in_model(&self) -> bool {
+        !matches!(self.current_token().token_type, TokenType::Public | TokenType::Private) && self.at_top_level_start()
     }
     
-    fn parse_var_decl(&mut self) -> Result<Statement, String> {
-        let var_type = self.parse_type()?;
+    /// Поле модели: `[public|private] тип имя [= значение]`
+    fn parse_field(&mut self) -> Result<FieldDef, ParseError> {
+        let start = self.current_span();
+        let doc = self.doc_comments();
+        let is_public = match self.current_token().token_type {
+            TokenType::Public => { self.advance(); true }
+            TokenType::Private => { self.advance(); false }
+            _ => false,
+        };
+        let field_type = self.parse_type()?;
         let name = match &self.current_token().token_type {
             TokenType::Identifier(n) => {
-                let vn = n.clone();
+                let name = n.clone();
                 self.advance();
-                vn
+                name
             }
-            _ => return Err("Expected variable name".to_string()),
+            _ => return Err(self.expected_name("field name")),
         };
-        let value = if matches!(self.current_token().token_type, TokenType::Equal) {
+        let default = if matches!(self.current_token().token_type, TokenType::Equal) {
             self.advance();
             Some(self.parse_expr()?)
         } else {
             None
         };
-        Ok(Statement::VarDecl {
-            name,
-            var_type,
-            value,
-            is_public: false,
-        })
+        Ok(FieldDef { name, field_type, default, is_public, doc, span: self.span_from(start) })
     }
     
-    fn parse_return(&mut self) -> Result<Statement, String> {
-        self.expect(TokenType::Return)?;
-        let expr = if matches!(
-            self.current_token().token_type,
-            TokenType::End | TokenType::Eof
-        ) {
-            None
-        } else {
-            Some(self.parse_expr()?)
+    fn parse_enum(&mut self, is_public: bool, start: Span, doc: Vec<String>) -> Result<TopLevel, ParseError> {
+        self.expect(TokenType::Enum)?;
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let name = n.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(self.expected_name("enum name")),
         };
-        Ok(Statement::Return(expr))
+        let mut variants = Vec::new();
+        while !matches!(self.current_token().token_type, TokenType::End) && !self.at_top_level_start() {
+            let before = self.pos;
+            match self.parse_variant() {
+                Ok(variant) => variants.push(variant),
+                Err(err) => {
+                    self.report(err);
+                    self.recover(false, before);
+                }
+            }
+        }
+        self.expect(TokenType::End)?;
+        Ok(TopLevel::Enum(EnumDef { name, variants, is_public, doc, span: self.span_from(start) }))
     }
     
-    fn parse_if(&mut self) -> Result<Statement, String> {
-        self.expect(TokenType::If)?;
-        let condition = self.parse_expr()?;
-        let body = self.parse_block()?;
-        let else_body = if matches!(self.current_token().token_type, TokenType::Else) {
-            self.advance();
-            Some(self.parse_block()?)
-        } else {
-            None
+    /// `error_code Имя = статус "текст"`; текст необязателен
+    fn parse_error_code(&mut self, start: Span, doc: Vec<String>) -> Result<TopLevel, ParseError> {
+        self.expect(TokenType::ErrorCode)?;
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let name = n.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(self.expected_name("error code name")),
+        };
+        self.expect(TokenType::Equal)?;
+        let status = match &self.current_token().token_type {
+            TokenType::Number(n) => n
+                .replace('_', "")
+                .parse::<u16>()
+                .ok()
+                .filter(|status| (400..=599).contains(status))
+                .ok_or_else(|| ParseError::InvalidStatus {
+                    span: self.current_span(),
+                    found: self.current_token().token_type.clone(),
+                })?,
+            found => {
+                return Err(ParseError::UnexpectedToken {
+                    span: self.current_span(),
+                    expected: vec![TokenType::Number(String::new())],
+                    found: found.clone(),
+                })
+            }
+        };
+        self.advance();
+        let message = match &self.current_token().token_type {
+            TokenType::String(text) => {
+                let text = text.clone();
+                self.advance();
+                Some(text)
+            }
+            _ => None,
         };
-        Ok(Statement::If { condition, body, else_body })
+        Ok(TopLevel::ErrorCode(ErrorCodeDef { name, status, message, doc, span: self.span_from(start) }))
     }
     
-    fn parse_while(&mut self) -> Result<Statement, String> {
+    /// `type Имя = тип`
+    fn parse_type_alias(&mut self, start: Span, doc: Vec<String>) -> Result<TopLevel, ParseError> {
+        self.advance();
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let name = n.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(self.expected_name("type name")),
+        };
+        self.expect(TokenType::Equal)?;
+        let target = self.parse_type()?;
+        Ok(TopLevel::TypeAlias(TypeAliasDef { name, target, doc, span: self.span_from(start) }))
+    }
+    
+    /// Вариант перечисления: `Имя` или `Имя(поле: тип, ...)`
+    fn parse_variant(&mut self) -> Result<VariantDef, ParseError> {
+        let start = self.current_span();
+        let doc = self.doc_comments();
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let name = n.clone();
+                self.advance();
+                name
+            }
+            _ => return Err(self.expected_name("variant name")),
+        };
+        let mut fields = Vec::new();
+        if matches!(self.current_token().token_type, TokenType::LeftParen) {
+            self.advance();
+            while !matches!(self.current_token().token_type, TokenType::RightParen) {
+                let field_name = match &self.current_token().token_type {
+                    TokenType::Identifier(n) => {
+                        let name = n.clone();
+                        self.advance();
+                        name
+                    }
+                    _ => return Err(self.expected_name("field name")),
+                };
+                self.expect(TokenType::Colon)?;
+                fields.push((field_name, self.parse_type()?));
+                if matches!(self.current_token().token_type, TokenType::Comma) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.expect(TokenType::RightParen)?;
+        }
+        Ok(VariantDef { name, fields, doc, span: self.span_from(start) })
+    }
+    
+    fn parse_route(&mut self, doc: Vec<String>) -> Result<TopLevel, ParseError> {
+        let start = self.current_span();
+        self.expect(TokenType::Route)?;
+        let path = match &self.current_token().token_type {
+            TokenType::String(p) => {
+                let path = p.clone();
+                self.advance();
+                path
+            }
+            _ => {
+                return Err(ParseError::ExpectedRoutePath {
+                    span: self.current_span(),
+                    found: self.current_token().token_type.clone(),
+                })
+            }
+        };
+        let method = match &self.current_token().token_type {
+            TokenType::Get => { self.advance(); "GET".to_string() }
+            TokenType::Post => { self.advance(); "POST".to_string() }
+            TokenType::Put => { self.advance(); "PUT".to_string() }
+            TokenType::Delete => { self.advance(); "DELETE".to_string() }
+            _ => {
+                return Err(ParseError::ExpectedHttpMethod {
+                    span: self.current_span(),
+                    found: self.current_token().token_type.clone(),
+                })
+            }
+        };
+        let body = self.parse_block();
+        Ok(TopLevel::Route(RouteDef { path, method, body, doc, span: self.span_from(start) }))
+    }
+    
+    /// Тип; `?` после него делает тип необязательным: `int?`, `list<txt>?`
+    fn parse_type(&mut self) -> Result<DataType, ParseError> {
+        let ty = self.parse_base_type()?;
+        if matches!(self.current_token().token_type, TokenType::Question) {
+            self.advance();
+            return Ok(DataType::Optional(Box::new(ty)));
+        }
+        Ok(ty)
+    }
+    
+    fn parse_base_type(&mut self) -> Result<DataType, ParseError> {
+        match &self.current_token().token_type {
+            TokenType::Identifier(name) if name == "list" => {
+                self.advance();
+                self.expect(TokenType::Less)?;
+                let item = self.parse_type()?;
+                self.expect(TokenType::Greater)?;
+                Ok(DataType::List(Box::new(item)))
+            }
+            TokenType::Identifier(name) if name == "map" => {
+                self.advance();
+                self.expect(TokenType::Less)?;
+                let key = self.parse_type()?;
+                self.expect(TokenType::Comma)?;
+                let value = self.parse_type()?;
+                self.expect(TokenType::Greater)?;
+                Ok(DataType::Map(Box::new(key), Box::new(value)))
+            }
+            TokenType::Json => { self.advance(); Ok(DataType::Json) }
+            TokenType::Int => { self.advance(); Ok(DataType::Int) }
+            TokenType::Float => { self.advance(); Ok(DataType::Float) }
+            TokenType::Double => { self.advance(); Ok(DataType::Double) }
+            TokenType::Txt => { self.advance(); Ok(DataType::Txt) }
+            TokenType::Bool => { self.advance(); Ok(DataType::Bool) }
+            TokenType::Auto => { self.advance(); Ok(DataType::Auto) }
+            // Пользовательский тип; `модуль.Тип` — тип из импортированного файла
+            TokenType::Identifier(name) => {
+                let start = self.current_span();
+                let mut name = name.clone();
+                self.advance();
+                while matches!(self.current_token().token_type, TokenType::Dot) {
+                    self.advance();
+                    match &self.current_token().token_type {
+                        TokenType::Identifier(part) => {
+                            name = format!("{}.{}", name, part);
+                            self.advance();
+                        }
+                        _ => return Err(self.expected_name("type name")),
+                    }
+                }
+                Ok(DataType::Named { name, span: self.span_from(start) })
+            }
+            _ => Err(ParseError::ExpectedType {
+                span: self.current_span(),
+                found: self.current_token().token_type.clone(),
+            }),
+        }
+    }
+    
+    /// Разбирает операторы до `end`. Ошибочные операторы заменяются на
+    /// `StatementKind::Error`, отсутствующий `end` тоже попадает в диагностики.
+    fn parse_block(&mut self) -> Vec<Statement> {
+        let statements = self.parse_statements(&[]);
+        self.expect_block_end();
+        statements
+    }
+    
+    /// `end`, закрывающий блок; если его нет, ошибка записывается, а разбор продолжается
+    fn expect_block_end(&mut self) {
+        if matches!(self.current_token().token_type, TokenType::End) {
+            self.advance();
+        } else {
+            self.report(ParseError::UnexpectedToken {
+                span: self.current_span(),
+                expected: vec![TokenType::End],
+                found: self.current_token().token_type.clone(),
+            });
+        }
+    }
+    
+    /// Операторы до `end` или до одного из токенов `stops`, которые продолжают
+    /// конструкцию (`else`, `case`, ...). Закрывающий токен не поглощается.
+    fn parse_statements(&mut self, stops: &[TokenType]) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        while !matches!(self.current_token().token_type, TokenType::End)
+            && !self.at_any(stops)
+            && !self.at_top_level_start()
+        {
+            let start = self.current_span();
+            let before = self.pos;
+            let opens_block = self.opens_block();
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.report(err);
+                    self.recover(opens_block, before);
+                    statements.push(Statement::new(StatementKind::Error, self.span_from(start)));
+                }
+            }
+        }
+        statements
+    }
+    
+    /// Совпадает ли вид текущего токена с одним из `kinds`
+    fn at_any(&self, kinds: &[TokenType]) -> bool {
+        let current = std::mem::discriminant(&self.current_token().token_type);
+        kinds.iter().any(|kind| std::mem::discriminant(kind) == current)
+    }
+    
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.current_span();
+        let kind = match &self.current_token().token_type {
+            TokenType::Return => self.parse_return()?,
+            TokenType::If => self.parse_if()?,
+            TokenType::While => self.parse_while(None)?,
+            TokenType::For => self.parse_for(None)?,
+            TokenType::Identifier(label) if self.at_loop_label() => {
+                let label = label.clone();
+                self.advance();
+                self.advance();
+                if matches!(self.current_token().token_type, TokenType::While) {
+                    self.parse_while(Some(label))?
+                } else {
+                    self.parse_for(Some(label))?
+                }
+            }
+            TokenType::Break | TokenType::Continue => self.parse_loop_jump(),
+            TokenType::Raise => {
+                self.advance();
+                match &self.current_token().token_type {
+                    TokenType::Identifier(name) => {
+                        let name = name.clone();
+                        self.advance();
+                        StatementKind::Raise(name)
+                    }
+                    _ => return Err(self.expected_name("error code name")),
+                }
+            }
+            TokenType::Try => self.parse_try()?,
+            TokenType::Match => {
+                let (scrutinee, arms) = self.parse_match(false)?;
+                StatementKind::Match { scrutinee, arms }
+            }
+            _ => {
+                if self.is_var_decl() {
+                    let doc = self.doc_comments();
+                    self.parse_var_decl(doc)?
+                } else {
+                    self.parse_expr_statement()?
+                }
+            }
+        };
+        Ok(Statement::new(kind, self.span_from(start)))
+    }
+    
+    fn is_var_decl(&self) -> bool {
+        if matches!(self.current_token().token_type, TokenType::Auto) {
+            return true;
+        }
+        self.type_len(0)
+            .is_some_and(|len| matches!(self.peek_token(len).token_type, TokenType::Identifier(_)))
+    }
+    
+    /// Сколько токенов занимает запись типа, начиная с `offset`, если там тип
+    fn type_len(&self, offset: usize) -> Option<usize> {
+        let len = match &self.peek_token(offset).token_type {
+            TokenType::Identifier(name)
+                if name == "list" && matches!(self.peek_token(offset + 1).token_type, TokenType::Less) =>
+            {
+                let item = self.type_len(offset + 2)?;
+                matches!(self.peek_token(offset + 2 + item).token_type, TokenType::Greater).then_some(item + 3)
+            }
+            TokenType::Identifier(name)
+                if name == "map" && matches!(self.peek_token(offset + 1).token_type, TokenType::Less) =>
+            {
+                let key = self.type_len(offset + 2)?;
+                if !matches!(self.peek_token(offset + 2 + key).token_type, TokenType::Comma) {
+                    return None;
+                }
+                let value = self.type_len(offset + 3 + key)?;
+                matches!(self.peek_token(offset + 3 + key + value).token_type, TokenType::Greater)
+                    .then_some(key + value + 4)
+            }
+            TokenType::Identifier(_) => {
+                // `модуль.Тип`: имена через точку
+                let mut len = 1;
+                while matches!(self.peek_token(offset + len).token_type, TokenType::Dot)
+                    && matches!(self.peek_token(offset + len + 1).token_type, TokenType::Identifier(_))
+                {
+                    len += 2;
+                }
+                Some(len)
+            }
+            TokenType::Int | TokenType::Float | TokenType::Double | TokenType::Txt | TokenType::Bool
+            | TokenType::Auto | TokenType::Json => Some(1),
+            _ => None,
+        }?;
+        // Необязательный тип `T?`
+        Some(len + usize::from(matches!(self.peek_token(offset + len).token_type, TokenType::Question)))
+    }
+    
+    fn parse_var_decl(&mut self, doc: Vec<String>) -> Result<StatementKind, ParseError> {
+        let var_type = self.parse_type()?;
+        let name = match &self.current_token().token_type {
+            TokenType::Identifier(n) => {
+                let vn = n.clone();
+                self.advance();
+                vn
+            }
+            _ => return Err(self.expected_name("variable name")),
+        };
+        let value = if matches!(self.current_token().token_type, TokenType::Equal) {
+            self.advance();
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(StatementKind::VarDecl {
+            name,
+            var_type,
+            value,
+            is_public: false,
+            doc,
+        })
+    }
+    
+    /// Выражение-оператор или присваивание `target = value` / `target op= value`
+    fn parse_expr_statement(&mut self) -> Result<StatementKind, ParseError> {
+        let expr = self.parse_expr()?;
+        let op = match &self.current_token().token_type {
+            TokenType::Equal => None,
+            TokenType::PlusEqual => Some("+"),
+            TokenType::MinusEqual => Some("-"),
+            TokenType::StarEqual => Some("*"),
+            TokenType::SlashEqual => Some("/"),
+            TokenType::PercentEqual => Some("%"),
+            _ => return Ok(StatementKind::ExprStmt(expr)),
+        };
+        if !expr.is_place() {
+            return Err(ParseError::InvalidAssignTarget {
+                span: expr.span,
+                found: self.current_token().token_type.clone(),
+            });
+        }
+        self.advance();
+        let value = self.parse_expr()?;
+        Ok(StatementKind::Assign { target: expr, op: op.map(str::to_string), value })
+    }
+    
+    fn parse_return(&mut self) -> Result<StatementKind, ParseError> {
+        self.expect(TokenType::Return)?;
+        // Без значения `return` стоит последним в блоке: дальше `end` или следующая ветка
+        let expr = if matches!(
+            self.current_token().token_type,
+            TokenType::End | TokenType::Eof | TokenType::Case | TokenType::Elif | TokenType::Else
+                | TokenType::Catch | TokenType::Finally
+        ) {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        Ok(StatementKind::Return(expr))
+    }
+    
+    /// `if`, любое число `elif` / `else if` и необязательный `else` под одним `end`
+    fn parse_if(&mut self) -> Result<StatementKind, ParseError> {
+        const BRANCH_STOPS: &[TokenType] = &[TokenType::Elif, TokenType::Else];
+        self.expect(TokenType::If)?;
+        let condition = self.parse_expr()?;
+        let mut branches = vec![(condition, self.parse_statements(BRANCH_STOPS))];
+        let mut else_body = None;
+        loop {
+            match self.current_token().token_type {
+                TokenType::Elif => self.advance(),
+                TokenType::Else if matches!(self.peek_token(1).token_type, TokenType::If)
+                    && self.peek_token(1).span.line == self.current_span().line =>
+                {
+                    self.advance();
+                    self.advance();
+                }
+                TokenType::Else => {
+                    self.advance();
+                    else_body = Some(self.parse_statements(&[]));
+                    break;
+                }
+                _ => break,
+            }
+            let condition = self.parse_expr()?;
+            branches.push((condition, self.parse_statements(BRANCH_STOPS)));
+        }
+        self.expect_block_end();
+        Ok(StatementKind::If { branches, else_body })
+    }
+    
+    fn parse_while(&mut self, label: Option<String>) -> Result<StatementKind, ParseError> {
         self.expect(TokenType::While)?;
         let condition = self.parse_expr()?;
-        let body = self.parse_block()?;
-        Ok(Statement::While { condition, body })
+        let body = self.parse_loop_body(&label);
+        Ok(StatementKind::While { condition, body, label })
+    }
+    
+    /// Тело цикла: внутри него допустимы `break` и `continue`
+    fn parse_loop_body(&mut self, label: &Option<String>) -> Vec<Statement> {
+        self.loops.push(label.clone());
+        let body = self.parse_block();
+        self.loops.pop();
+        body
     }
     
-    fn parse_for(&mut self) -> Result<Statement, String> {
+    /// `break` / `continue` с необязательной меткой на той же строке. Вне цикла
+    /// и с неизвестной меткой ошибка записывается, а оператор всё равно строится.
+    fn parse_loop_jump(&mut self) -> StatementKind {
+        let keyword = self.current_token().token_type.clone();
+        let start = self.current_span();
+        self.advance();
+        let label = match &self.current_token().token_type {
+            TokenType::Identifier(name) if self.current_span().line == start.line => {
+                let name = name.clone();
+                let label_span = self.current_span();
+                self.advance();
+                if !self.loops.is_empty() && !self.loops.iter().any(|l| l.as_deref() == Some(name.as_str())) {
+                    self.report(ParseError::UndeclaredLabel {
+                        span: label_span,
+                        label: name.clone(),
+                        found: TokenType::Identifier(name.clone()),
+                    });
+                }
+                Some(name)
+            }
+            _ => None,
+        };
+        if self.loops.is_empty() {
+            self.report(ParseError::OutsideLoop { span: self.span_from(start), found: keyword.clone() });
+        }
+        if matches!(keyword, TokenType::Break) {
+            StatementKind::Break(label)
+        } else {
+            StatementKind::Continue(label)
+        }
+    }
+    
+    fn parse_for(&mut self, label: Option<String>) -> Result<StatementKind, ParseError> {
         self.expect(TokenType::For)?;
         let var = match &self.current_token().token_type {
             TokenType::Identifier(v) => {
@@ -330,155 +1400,918 @@ impl Parser {
                 self.advance();
                 vn
             }
-            _ => return Err("Expected variable name".to_string()),
+            _ => return Err(self.expected_name("variable name")),
         };
-        self.expect(TokenType::Equal)?;
+        let iter = if matches!(self.current_token().token_type, TokenType::Comma | TokenType::In) {
+            self.parse_for_each()?
+        } else {
+            self.parse_for_range()?
+        };
+        let body = self.parse_loop_body(&label);
+        Ok(StatementKind::For { var, iter, body, label })
+    }
+    
+    /// `[, value] in collection`
+    fn parse_for_each(&mut self) -> Result<ForIter, ParseError> {
+        let value = if matches!(self.current_token().token_type, TokenType::Comma) {
+            self.advance();
+            match &self.current_token().token_type {
+                TokenType::Identifier(v) => {
+                    let value = v.clone();
+                    self.advance();
+                    Some(value)
+                }
+                _ => return Err(self.expected_name("variable name")),
+            }
+        } else {
+            None
+        };
+        self.expect(TokenType::In)?;
+        let collection = self.parse_expr()?;
+        Ok(ForIter::Each { value, collection })
+    }
+    
+    /// `= start, end [, step]` или `= start to end [, step]`
+    fn parse_for_range(&mut self) -> Result<ForIter, ParseError> {
+        if !matches!(self.current_token().token_type, TokenType::Equal) {
+            return Err(ParseError::UnexpectedToken {
+                span: self.current_span(),
+                expected: vec![TokenType::Equal, TokenType::In],
+                found: self.current_token().token_type.clone(),
+            });
+        }
+        self.advance();
         let start = self.parse_expr()?;
-        self.expect(TokenType::Comma)?;
+        let inclusive = match &self.current_token().token_type {
+            TokenType::Identifier(word) if word == "to" => true,
+            TokenType::Comma => false,
+            found => {
+                return Err(ParseError::UnexpectedToken {
+                    span: self.current_span(),
+                    expected: vec![TokenType::Comma, TokenType::Identifier("to".to_string())],
+                    found: found.clone(),
+                })
+            }
+        };
+        self.advance();
         let end = self.parse_expr()?;
-        let body = self.parse_block()?;
-        Ok(Statement::For { var, start, end, body })
+        let step = if matches!(self.current_token().token_type, TokenType::Comma) {
+            self.advance();
+            Some(Box::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        Ok(ForIter::Range { start: Box::new(start), end: Box::new(end), step, inclusive })
     }
     
-    fn parse_try(&mut self) -> Result<Statement, String> {
+    /// `try ... catch [Код] [err] ... finally ... end`. Прежняя запись, где
+    /// каждый блок закрывается своим `end` (`try ... end catch ... end`), тоже принимается.
+    fn parse_try(&mut self) -> Result<StatementKind, ParseError> {
+        const CLAUSE_STOPS: &[TokenType] = &[TokenType::Catch, TokenType::Finally];
         self.expect(TokenType::Try)?;
-        let body = self.parse_block()?;
-        self.expect(TokenType::Catch)?;
-        let catch_body = self.parse_block()?;
-        Ok(Statement::Try { body, catch_body })
+        let body = self.parse_statements(CLAUSE_STOPS);
+        self.skip_end_before(CLAUSE_STOPS);
+        let mut catches = Vec::new();
+        while matches!(self.current_token().token_type, TokenType::Catch) {
+            let start = self.current_span();
+            self.advance();
+            let mut names = Vec::new();
+            while names.len() < 2 && self.current_span().line == start.line {
+                match &self.current_token().token_type {
+                    TokenType::Identifier(name) => {
+                        names.push(name.clone());
+                        self.advance();
+                    }
+                    _ => break,
+                }
+            }
+            let span = self.span_from(start);
+            // Одно имя — код ошибки, если такой объявлен или имя с заглавной буквы
+            // (необъявленный код найдёт проверка), иначе — переменная
+            let (code, var) = match names.len() {
+                2 => (names.first().cloned(), names.pop()),
+                _ => match names.pop() {
+                    Some(name) if self.error_codes.contains(&name) || name.starts_with(char::is_uppercase) => {
+                        (Some(name), None)
+                    }
+                    name => (None, name),
+                },
+            };
+            let var = var.filter(|var| var != "_");
+            let body = self.parse_statements(CLAUSE_STOPS);
+            self.skip_end_before(CLAUSE_STOPS);
+            catches.push(CatchClause { code, var, body, span });
+        }
+        let finally = if matches!(self.current_token().token_type, TokenType::Finally) {
+            self.advance();
+            Some(self.parse_statements(&[]))
+        } else {
+            None
+        };
+        if catches.is_empty() && finally.is_none() {
+            return Err(ParseError::UnexpectedToken {
+                span: self.current_span(),
+                expected: CLAUSE_STOPS.to_vec(),
+                found: self.current_token().token_type.clone(),
+            });
+        }
+        self.expect_block_end();
+        Ok(StatementKind::Try { body, catches, finally })
     }
     
-    fn parse_expr(&mut self) -> Result<Expr, String> {
-        self.parse_or()
+    /// `end`, за которым сразу идёт продолжение конструкции (`end catch`)
+    fn skip_end_before(&mut self, stops: &[TokenType]) {
+        if matches!(self.current_token().token_type, TokenType::End) {
+            let next = std::mem::discriminant(&self.peek_token(1).token_type);
+            if stops.iter().any(|kind| std::mem::discriminant(kind) == next) {
+                self.advance();
+            }
+        }
     }
     
-    fn parse_or(&mut self) -> Result<Expr, String> {
-        let mut left = self.parse_and()?;
-        while matches!(self.current_token().token_type, TokenType::Or) {
+    /// `match x` и ветки `case` до `end`. В выражении (`as_expr`) у каждой ветки
+    /// должно быть значение `=> выражение`.
+    fn parse_match(&mut self, as_expr: bool) -> Result<(Expr, Vec<MatchArm>), ParseError> {
+        self.expect(TokenType::Match)?;
+        let scrutinee = self.parse_expr()?;
+        let mut arms = Vec::new();
+        while matches!(self.current_token().token_type, TokenType::Case) {
             self.advance();
-            let right = self.parse_and()?;
-            left = Expr::BinaryOp {
-                left: Box::new(left),
-                op: "||".to_string(),
-                right: Box::new(right),
+            let start = self.current_span();
+            let pattern = self.parse_pattern()?;
+            let span = self.span_from(start);
+            let body = if matches!(self.current_token().token_type, TokenType::FatArrow) {
+                self.advance();
+                ArmBody::Value(self.parse_expr()?)
+            } else if as_expr {
+                return Err(ParseError::UnexpectedToken {
+                    span: self.current_span(),
+                    expected: vec![TokenType::FatArrow],
+                    found: self.current_token().token_type.clone(),
+                });
+            } else {
+                ArmBody::Block(self.parse_statements(&[TokenType::Case]))
             };
+            arms.push(MatchArm { pattern, body, span });
         }
-        Ok(left)
+        if !matches!(self.current_token().token_type, TokenType::End) {
+            return Err(ParseError::UnexpectedToken {
+                span: self.current_span(),
+                expected: vec![TokenType::Case, TokenType::End],
+                found: self.current_token().token_type.clone(),
+            });
+        }
+        self.advance();
+        Ok((scrutinee, arms))
     }
     
-    fn parse_and(&mut self) -> Result<Expr, String> {
-        let mut left = self.parse_comparison()?;
-        while matches!(self.current_token().token_type, TokenType::And) {
-            self.advance();
-            let right = self.parse_comparison()?;
-            left = Expr::BinaryOp {
-                left: Box::new(left),
-                op: "&&".to_string(),
-                right: Box::new(right),
-            };
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let start = self.current_span();
+        match &self.current_token().token_type {
+            TokenType::Identifier(name) if name == "_" => {
+                self.advance();
+                Ok(Pattern::Wildcard)
+            }
+            TokenType::Identifier(name) if name == "true" || name == "false" => {
+                let literal = Expr::new(ExprKind::Identifier(name.clone()), start);
+                self.advance();
+                Ok(Pattern::Literal(literal))
+            }
+            TokenType::Number(_) | TokenType::String(_) => Ok(Pattern::Literal(self.parse_primary()?)),
+            TokenType::Minus if matches!(self.peek_token(1).token_type, TokenType::Number(_)) => {
+                self.advance();
+                let operand = self.parse_primary()?;
+                let kind = ExprKind::Unary { op: UnaryOp::Neg, operand: Box::new(operand) };
+                Ok(Pattern::Literal(Expr::new(kind, self.span_from(start))))
+            }
+            TokenType::Identifier(name) => {
+                let mut enum_name = None;
+                let mut variant = name.clone();
+                self.advance();
+                if matches!(self.current_token().token_type, TokenType::Dot) {
+                    self.advance();
+                    enum_name = Some(variant);
+                    variant = match &self.current_token().token_type {
+                        TokenType::Identifier(n) => n.clone(),
+                        _ => return Err(self.expected_name("variant name")),
+                    };
+                    self.advance();
+                }
+                let mut bindings = Vec::new();
+                if matches!(self.current_token().token_type, TokenType::LeftParen) {
+                    self.advance();
+                    while !matches!(self.current_token().token_type, TokenType::RightParen) {
+                        match &self.current_token().token_type {
+                            TokenType::Identifier(n) => bindings.push(n.clone()),
+                            _ => return Err(self.expected_name("binding name")),
+                        }
+                        self.advance();
+                        if matches!(self.current_token().token_type, TokenType::Comma) {
+                            self.advance();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect(TokenType::RightParen)?;
+                }
+                Ok(Pattern::Variant { enum_name, variant, bindings })
+            }
+            _ => Err(ParseError::ExpectedPattern {
+                span: start,
+                found: self.current_token().token_type.clone(),
+            }),
         }
-        Ok(left)
     }
     
-    fn parse_comparison(&mut self) -> Result<Expr, String> {
-        let mut left = self.parse_additive()?;
-        loop {
-            let op = match &self.current_token().token_type {
-                TokenType::EqualEqual => "==",
-                TokenType::NotEqual => "!=",
-                TokenType::Less => "<",
-                TokenType::Greater => ">",
-                TokenType::LessEqual => "<=",
-                TokenType::GreaterEqual => ">=",
-                _ => break,
-            };
-            self.advance();
-            let right = self.parse_additive()?;
-            left = Expr::BinaryOp {
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(0)
+    }
+    
+    fn binary(left: Expr, op: BinOp, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
+        Expr::new(
+            ExprKind::BinaryOp {
                 left: Box::new(left),
-                op: op.to_string(),
+                op,
                 right: Box::new(right),
-            };
+            },
+            span,
+        )
+    }
+    
+    /// Разбирает выражение из `{...}` внутри строки отдельным парсером
+    fn parse_interpolation(tokens: Vec<Token>) -> Result<Expr, ParseError> {
+        let mut parser = Parser::new(tokens);
+        let expr = parser.parse_expr()?;
+        if !matches!(parser.current_token().token_type, TokenType::Eof) {
+            return Err(ParseError::UnexpectedToken {
+                span: parser.current_span(),
+                expected: vec![TokenType::RightBrace],
+                found: parser.current_token().token_type.clone(),
+            });
         }
-        Ok(left)
+        Ok(expr)
     }
     
-    fn parse_additive(&mut self) -> Result<Expr, String> {
-        let mut left = self.parse_multiplicative()?;
-        loop {
-            let op = match &self.current_token().token_type {
-                TokenType::Plus => "+",
-                TokenType::Minus => "-",
-                _ => break,
-            };
+    /// Precedence climbing: разбирает операторы с приоритетом не ниже `min_precedence`
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+        while let Some(op) = BinOp::from_token(&self.current_token().token_type) {
+            let precedence = op.precedence();
+            if precedence < min_precedence {
+                break;
+            }
             self.advance();
-            let right = self.parse_multiplicative()?;
-            left = Expr::BinaryOp {
-                left: Box::new(left),
-                op: op.to_string(),
-                right: Box::new(right),
+            let next_min = match op.assoc() {
+                Assoc::Right => precedence,
+                Assoc::Left | Assoc::None => precedence + 1,
             };
+            let right = self.parse_binary(next_min)?;
+            if op.assoc() == Assoc::None {
+                if let Some(next) = BinOp::from_token(&self.current_token().token_type) {
+                    if next.precedence() == precedence {
+                        return Err(ParseError::ChainedComparison {
+                            span: self.current_span(),
+                            found: self.current_token().token_type.clone(),
+                        });
+                    }
+                }
+            }
+            left = Self::binary(left, op, right);
         }
         Ok(left)
     }
     
-    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
-        let mut left = self.parse_primary()?;
+    /// Префиксные `-`, `!` и `not` связывают сильнее `*`; операнд может содержать `**`
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        let op = match &self.current_token().token_type {
+            TokenType::Minus => UnaryOp::Neg,
+            TokenType::Not => UnaryOp::Not,
+            _ => return self.parse_postfix(),
+        };
+        self.advance();
+        let operand = self.parse_binary(UNARY_PRECEDENCE + 1)?;
+        let span = start.to(operand.span);
+        Ok(Expr::new(ExprKind::Unary { op, operand: Box::new(operand) }, span))
+    }
+    
+    /// Цепочка `a.b().c[0]`: поля, вызовы методов и индексация. `[` на новой
+    /// строке начинает уже следующий оператор, а `.` продолжает цепочку.
+    fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
+        let mut expr = self.parse_primary()?;
         loop {
-            let op = match &self.current_token().token_type {
-                TokenType::Star => "*",
-                TokenType::Slash => "/",
-                TokenType::Percent => "%",
-                _ => break,
-            };
-            self.advance();
-            let right = self.parse_primary()?;
-            left = Expr::BinaryOp {
-                left: Box::new(left),
-                op: op.to_string(),
-                right: Box::new(right),
+            match &self.current_token().token_type {
+                TokenType::LeftBracket if self.current_span().line == self.previous_span().end_line => {
+                    self.advance();
+                    let index = self.parse_expr()?;
+                    self.expect(TokenType::RightBracket)?;
+                    expr = Expr::new(
+                        ExprKind::Index { target: Box::new(expr), index: Box::new(index) },
+                        self.span_from(start),
+                    );
+                }
+                TokenType::Dot => {
+                    self.advance();
+                    let name = match &self.current_token().token_type {
+                        TokenType::Identifier(name) => name.clone(),
+                        _ => return Err(self.expected_name("field or method name")),
+                    };
+                    self.advance();
+                    let kind = if matches!(self.current_token().token_type, TokenType::LeftParen) {
+                        let args = self.parse_args()?;
+                        ExprKind::MethodCall { target: Box::new(expr), name, args }
+                    } else {
+                        ExprKind::Field { target: Box::new(expr), name }
+                    };
+                    expr = Expr::new(kind, self.span_from(start));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+    
+    /// `{` после имени начинает создание модели, если за ней `}` или `поле:`
+    fn at_construct(&self) -> bool {
+        matches!(self.current_token().token_type, TokenType::LeftBrace)
+            && self.current_span().line == self.previous_span().end_line
+            && match self.peek_token(1).token_type {
+                TokenType::RightBrace => true,
+                TokenType::Identifier(_) => matches!(self.peek_token(2).token_type, TokenType::Colon),
+                _ => false,
+            }
+    }
+    
+    fn parse_construct(&mut self, model: String, start: Span) -> Result<Expr, ParseError> {
+        self.expect(TokenType::LeftBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(self.current_token().token_type, TokenType::RightBrace) {
+            let name = match &self.current_token().token_type {
+                TokenType::Identifier(n) => {
+                    let name = n.clone();
+                    self.advance();
+                    name
+                }
+                _ => return Err(self.expected_name("field name")),
             };
+            self.expect(TokenType::Colon)?;
+            fields.push((name, self.parse_expr()?));
+            if matches!(self.current_token().token_type, TokenType::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
         }
-        Ok(left)
+        self.expect(TokenType::RightBrace)?;
+        Ok(Expr::new(ExprKind::Construct { model, fields }, self.span_from(start)))
     }
     
-    fn parse_primary(&mut self) -> Result<Expr, String> {
+    /// Аргументы вызова `(a, b)`
+    fn parse_args(&mut self) -> Result<Vec<Expr>, ParseError> {
+        self.expect(TokenType::LeftParen)?;
+        let mut args = Vec::new();
+        while !matches!(self.current_token().token_type, TokenType::RightParen) {
+            args.push(self.parse_expr()?);
+            if matches!(self.current_token().token_type, TokenType::Comma) {
+                self.advance();
+            }
+        }
+        self.expect(TokenType::RightParen)?;
+        Ok(args)
+    }
+    
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span();
         match &self.current_token().token_type {
             TokenType::Number(n) => {
                 let num = n.clone();
                 self.advance();
-                Ok(Expr::Literal(num))
+                Ok(Expr::new(ExprKind::Literal(Literal::from_number(&num)), start))
             }
             TokenType::String(s) => {
                 let string = s.clone();
                 self.advance();
-                Ok(Expr::Literal(format!("\"{}\"", string)))
+                Ok(Expr::new(ExprKind::Literal(Literal::Str(string)), start))
             }
             TokenType::Identifier(name) => {
                 let id = name.clone();
                 self.advance();
+                if self.at_construct() {
+                    return self.parse_construct(id, start);
+                }
                 if matches!(self.current_token().token_type, TokenType::LeftParen) {
-                    self.advance();
-                    let mut args = Vec::new();
-                    while !matches!(self.current_token().token_type, TokenType::RightParen) {
-                        args.push(self.parse_expr()?);
-                        if matches!(self.current_token().token_type, TokenType::Comma) {
-                            self.advance();
-                        }
-                    }
-                    self.expect(TokenType::RightParen)?;
-                    Ok(Expr::FunctionCall { name: id, args })
+                    let args = self.parse_args()?;
+                    Ok(Expr::new(ExprKind::FunctionCall { name: id, args }, self.span_from(start)))
                 } else {
-                    Ok(Expr::Identifier(id))
+                    Ok(Expr::new(ExprKind::Identifier(id), start))
                 }
             }
+            TokenType::InterpolatedString(parts) => {
+                let parts = parts.clone();
+                self.advance();
+                let mut result = Vec::new();
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => result.push(InterpolationPart::Text(text)),
+                        StringPart::Code(tokens) => result.push(InterpolationPart::Expr(Self::parse_interpolation(tokens)?)),
+                    }
+                }
+                Ok(Expr::new(ExprKind::Interpolated(result), start))
+            }
+            TokenType::Nil => {
+                self.advance();
+                Ok(Expr::new(ExprKind::Nil, start))
+            }
+            TokenType::Error => {
+                self.advance();
+                Ok(Expr::new(ExprKind::Error, start))
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut items = Vec::new();
+                while !matches!(self.current_token().token_type, TokenType::RightBracket) {
+                    items.push(self.parse_expr()?);
+                    if matches!(self.current_token().token_type, TokenType::Comma) {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(TokenType::RightBracket)?;
+                Ok(Expr::new(ExprKind::List(items), self.span_from(start)))
+            }
+            TokenType::LeftBrace => {
+                self.advance();
+                let mut entries = Vec::new();
+                while !matches!(self.current_token().token_type, TokenType::RightBrace) {
+                    let key = self.parse_expr()?;
+                    self.expect(TokenType::Colon)?;
+                    let value = self.parse_expr()?;
+                    entries.push((key, value));
+                    if matches!(self.current_token().token_type, TokenType::Comma) {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(TokenType::RightBrace)?;
+                Ok(Expr::new(ExprKind::Map(entries), self.span_from(start)))
+            }
+            TokenType::Match => {
+                let (scrutinee, arms) = self.parse_match(true)?;
+                let kind = ExprKind::Match { scrutinee: Box::new(scrutinee), arms };
+                Ok(Expr::new(kind, self.span_from(start)))
+            }
             TokenType::LeftParen => {
                 self.advance();
-                let expr = self.parse_expr()?;
+                let mut expr = self.parse_expr()?;
                 self.expect(TokenType::RightParen)?;
+                expr.span = self.span_from(start);
                 Ok(expr)
             }
-            _ => Err(format!("Unexpected token: {:?}", self.current_token().token_type)),
+            _ => Err(ParseError::ExpectedExpression {
+                span: start,
+                found: self.current_token().token_type.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::codegen::lexer::Lexer;
+
+    fn parse(source: &str) -> ParseResult {
+        let tokens = Lexer::new(source).tokenize();
+        Parser::new(tokens).parse()
+    }
+
+    /// Тело первой функции файла
+    fn function_body(result: &ParseResult) -> &[Statement] {
+        match result.items.first() {
+            Some(TopLevel::Function(func)) => &func.body,
+            other => panic!("expected a function, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ast_nodes_carry_spans() {
+        let source = "func add(a: int, b: int) -> int\n    return a + b\nend\n";
+        let result = parse(source);
+        assert!(!result.has_errors());
+        let func_span = result.items[0].span();
+        assert_eq!(&source[func_span.start..func_span.end], "func add(a: int, b: int) -> int\n    return a + b\nend");
+        let stmt = &function_body(&result)[0];
+        assert_eq!(&source[stmt.span.start..stmt.span.end], "return a + b");
+        assert_eq!((stmt.span.line, stmt.span.column), (2, 5));
+        let StatementKind::Return(Some(value)) = &stmt.kind else {
+            panic!("expected return");
+        };
+        assert_eq!(&source[value.span.start..value.span.end], "a + b");
+        let ExprKind::BinaryOp { right, .. } = &value.kind else {
+            panic!("expected binary operator");
+        };
+        assert_eq!((right.span.line, right.span.column), (2, 16));
+    }
+
+    /// Код, строка и колонка каждой диагностики
+    fn errors(result: &ParseResult) -> Vec<(&'static str, usize, usize)> {
+        result.diagnostics.iter().map(|d| (d.code, d.span.line, d.span.column)).collect()
+    }
+
+    #[test]
+    fn structured_errors_point_at_offending_token() {
+        assert_eq!(errors(&parse("func f(a: 5)\nend\n")), [("K0003", 1, 11)]);
+        assert_eq!(errors(&parse("route 5 get\nend\n")), [("K0005", 1, 7)]);
+        assert_eq!(errors(&parse("route \"/users\" fetch\nend\n")), [("K0006", 1, 16)]);
+        let result = parse("func f(a int)\nend\n");
+        assert_eq!(errors(&result), [("K0001", 1, 10)]);
+        assert_eq!(result.diagnostics[0].message, "expected `:`, found `int`");
+    }
+
+    #[test]
+    fn recovery_reports_every_error_and_keeps_good_items() {
+        let result = parse(
+            "func broken(a int)\n    return a\nend\n\
+             route 42 get\n    return 1\nend\n\
+             func good() -> int\n    return 1\nend\n",
+        );
+        assert_eq!(errors(&result), [("K0001", 1, 15), ("K0005", 4, 7)]);
+        assert!(matches!(result.items[0], TopLevel::Error(_)));
+        assert!(matches!(result.items[1], TopLevel::Error(_)));
+        assert!(matches!(&result.items[2], TopLevel::Function(func) if func.name == "good"));
+        assert_eq!(result.items.len(), 3);
+    }
+
+    #[test]
+    fn recovery_inside_block_leaves_error_statement() {
+        let result = parse("func f() -> int\n    return )\n    return 1\nend\n");
+        assert_eq!(errors(&result), [("K0004", 2, 12)]);
+        let body = function_body(&result);
+        assert!(matches!(body[0].kind, StatementKind::Error));
+        assert!(matches!(body[1].kind, StatementKind::Return(Some(_))));
+    }
+
+    #[test]
+    fn recovery_keeps_block_after_unterminated_expression() {
+        let result = parse(
+            "func f(y: bool)\n    auto x = (1 +\n    if y\n        return\n    end\nend\nfunc g()\nend\n",
+        );
+        assert_eq!(errors(&result), [("K0004", 3, 5)]);
+        let body = function_body(&result);
+        assert!(matches!(body[0].kind, StatementKind::Error));
+        assert!(matches!(body[1].kind, StatementKind::If { .. }));
+        assert_eq!(result.items.len(), 2);
+    }
+
+    #[test]
+    fn recovery_stops_at_next_top_level_keyword() {
+        let result = parse("func f(\nimport math from math\nfunc g()\nend\n");
+        assert_eq!(errors(&result).len(), 1);
+        assert!(matches!(result.items[1], TopLevel::Import { .. }));
+        assert!(matches!(&result.items[2], TopLevel::Function(func) if func.name == "g"));
+    }
+
+    #[test]
+    fn recovery_skips_visibility_inside_broken_block() {
+        let result = parse("func f()\n    while )\n        private x\n    end\n    return\nend\nfunc g()\nend\n");
+        assert_eq!(errors(&result), [("K0004", 2, 11)]);
+        assert_eq!(function_body(&result).len(), 2);
+        assert!(matches!(&result.items[1], TopLevel::Function(func) if func.name == "g"));
+    }
+
+    #[test]
+    fn doc_comments_attach_to_following_item() {
+        let result = parse(
+            "/// Adds numbers\n/// together\nfunc add(a: int) -> int\n    /// Twice\n    auto b = a * 2\n    return b\nend\n\
+             /// Lists users\nroute \"/users\" get\n    return 1\nend\n",
+        );
+        assert!(!result.has_errors());
+        let Some(TopLevel::Function(func)) = result.items.first() else {
+            panic!("expected a function");
+        };
+        assert_eq!(func.doc, ["Adds numbers", "together"]);
+        assert!(matches!(&func.body[0].kind, StatementKind::VarDecl { doc, .. } if doc == &["Twice"]));
+        assert!(matches!(&result.items[1], TopLevel::Route(route) if route.doc == ["Lists users"]));
+    }
+
+    #[test]
+    fn interpolated_string_parses_expressions() {
+        let result = parse("func f(a: int) -> txt\n    return \"sum {a + 1}\"\nend\n");
+        assert!(!result.has_errors());
+        let StatementKind::Return(Some(value)) = &function_body(&result)[0].kind else {
+            panic!("expected return");
+        };
+        let ExprKind::Interpolated(parts) = &value.kind else {
+            panic!("expected interpolation");
+        };
+        assert!(matches!(&parts[0], InterpolationPart::Text(text) if text == "sum "));
+        assert!(matches!(&parts[1], InterpolationPart::Expr(e) if matches!(e.kind, ExprKind::BinaryOp { op: BinOp::Add, .. })));
+        assert_eq!(parts.len(), 2);
+    }
+
+    #[test]
+    fn number_literals_parse_value_and_suffix() {
+        assert!(matches!(
+            Literal::from_number("0xFF_FFu32"),
+            Literal::Int { value: 0xFFFF, suffix: Some(ref suffix), .. } if suffix == "u32"
+        ));
+        assert!(matches!(Literal::from_number("0b1010"), Literal::Int { value: 10, suffix: None, .. }));
+        assert!(matches!(Literal::from_number("1.5e-3"), Literal::Float { value, .. } if value == 1.5e-3));
+        assert!(matches!(
+            Literal::from_number("2.0f32"),
+            Literal::Float { suffix: Some(ref suffix), .. } if suffix == "f32"
+        ));
+    }
+
+    #[test]
+    fn compound_assignment_keeps_operator() {
+        let result = parse("func f()\n    x -= 2\nend\n");
+        assert!(!result.has_errors());
+        let StatementKind::Assign { target, op, .. } = &function_body(&result)[0].kind else {
+            panic!("expected assignment");
+        };
+        assert!(matches!(&target.kind, ExprKind::Identifier(name) if name == "x"));
+        assert_eq!(op.as_deref(), Some("-"));
+    }
+
+    #[test]
+    fn compound_assignment_to_non_place() {
+        let result = parse("func f()\n    1 += 2\nend\n");
+        assert_eq!(errors(&result), [("K0007", 2, 5)]);
+        assert_eq!(result.diagnostics[0].message, "invalid left-hand side of `+=`");
+    }
+
+    #[test]
+    fn assignment_targets_are_places() {
+        let result = parse("func f()\n    x = 1\n    user.name = \"a\"\n    xs[0] = 2\nend\n");
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let targets: Vec<_> = function_body(&result)
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StatementKind::Assign { target, op: None, .. } => target.is_place(),
+                other => panic!("expected assignment, found {:?}", other),
+            })
+            .collect();
+        assert_eq!(targets, [true, true, true]);
+    }
+
+    #[test]
+    fn assignment_to_call_is_rejected() {
+        let result = parse("func f()\n    g() = 1\nend\n");
+        assert_eq!(errors(&result), [("K0007", 2, 5)]);
+    }
+
+    /// Значение первого `return` первой функции
+    fn returned(source: &str) -> Expr {
+        let result = parse(source);
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        match &function_body(&result)[0].kind {
+            StatementKind::Return(Some(value)) => value.clone(),
+            other => panic!("expected return, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unary_binds_tighter_than_multiplication() {
+        let value = returned("func f(a: int, b: int) -> int\n    return -a * b\nend\n");
+        let ExprKind::BinaryOp { left, op: BinOp::Mul, .. } = &value.kind else {
+            panic!("expected multiplication, found {:?}", value.kind);
+        };
+        assert!(matches!(&left.kind, ExprKind::Unary { op: UnaryOp::Neg, .. }));
+        let value = returned("func f(done: bool) -> bool\n    return not done\nend\n");
+        assert!(matches!(&value.kind, ExprKind::Unary { op: UnaryOp::Not, .. }));
+    }
+
+    /// Выражение в виде скобочной записи: структура дерева без учёта исходных скобок
+    fn shape(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::BinaryOp { left, op, right } => format!("({} {} {})", shape(left), op.to_rust(), shape(right)),
+            ExprKind::Unary { op, operand } => format!("{}{}", op.to_rust(), shape(operand)),
+            ExprKind::Identifier(name) => name.clone(),
+            other => format!("{:?}", other),
         }
     }
+
+    #[test]
+    fn precedence_and_associativity_follow_the_table() {
+        let cases = [
+            ("a + b * c", "(a + (b * c))"),
+            ("(a + b) * c", "((a + b) * c)"),
+            ("a - b - c", "((a - b) - c)"),
+            ("a ** b ** c", "(a ** (b ** c))"),
+            ("-a ** b", "-(a ** b)"),
+            ("a or b and c == d", "(a || (b && (c == d)))"),
+        ];
+        for (source, expected) in cases {
+            let value = returned(&format!("func f()\n    return {}\nend\n", source));
+            assert_eq!(shape(&value), expected, "{}", source);
+        }
+        assert!(BinOp::Mul.precedence() > BinOp::Add.precedence());
+        assert_eq!(BinOp::Pow.assoc(), Assoc::Right);
+    }
+
+    #[test]
+    fn chained_comparison_is_rejected() {
+        let result = parse("func f(a: int) -> bool\n    return 1 < a < 3\nend\n");
+        assert_eq!(errors(&result), [("K0008", 2, 18)]);
+    }
+
+    #[test]
+    fn list_type_literal_and_index() {
+        let result = parse("func f(xs: list<list<int>>) -> int\n    auto ys = [1, 2, 3,]\n    return xs[0][ys[1]]\nend\n");
+        assert!(errors(&result).is_empty());
+        let TopLevel::Function(function) = &result.items[0] else { panic!("expected a function") };
+        assert_eq!(function.params[0].1.to_string(), "list<list<int>>");
+        assert_eq!(function.params[0].1.to_rust(), "Vec<Vec<i32>>");
+        let StatementKind::VarDecl { value: Some(value), .. } = &function_body(&result)[0].kind else {
+            panic!("expected a declaration")
+        };
+        assert!(matches!(&value.kind, ExprKind::List(items) if items.len() == 3));
+        let ExprKind::Index { target, .. } = returned("func f()\n    return xs[0][1]\nend\n").kind else {
+            panic!("expected an index")
+        };
+        assert!(matches!(target.kind, ExprKind::Index { .. }));
+    }
+
+    #[test]
+    fn map_type_and_literal() {
+        let result = parse("func f(m: map<txt, list<int>>)\n    auto e = {}\n    auto m2 = {\"a\": 1, \"b\": 2}\nend\n");
+        assert!(errors(&result).is_empty());
+        let TopLevel::Function(function) = &result.items[0] else { panic!("expected a function") };
+        assert_eq!(function.params[0].1.to_string(), "map<txt, list<int>>");
+        assert_eq!(function.params[0].1.to_rust(), "HashMap<String, Vec<i32>>");
+        let sizes: Vec<usize> = function_body(&result)
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StatementKind::VarDecl { value: Some(Expr { kind: ExprKind::Map(entries), .. }), .. } => entries.len(),
+                other => panic!("expected a map declaration, found {:?}", other),
+            })
+            .collect();
+        assert_eq!(sizes, [0, 2]);
+    }
+
+    #[test]
+    fn postfix_chain_nests_left_to_right() {
+        let value = returned("func f()\n    return a.b(1, 2).c[0]\nend\n");
+        let ExprKind::Index { target, .. } = value.kind else { panic!("expected an index") };
+        let ExprKind::Field { target, name } = target.kind else { panic!("expected a field") };
+        assert_eq!(name, "c");
+        let ExprKind::MethodCall { target, name, args } = target.kind else { panic!("expected a method call") };
+        assert_eq!((name.as_str(), args.len()), ("b", 2));
+        assert!(matches!(target.kind, ExprKind::Identifier(ref name) if name == "a"));
+    }
+
+    #[test]
+    fn bare_return_before_case() {
+        let result = parse(
+            "enum Color\n    Red\n    Green\nend\n\
+             func paint(c: Color)\n    match c\n        case Color.Red\n            return\n        case Color.Green\n            return\n    end\nend\n",
+        );
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let Some(TopLevel::Function(func)) = result.items.get(1) else {
+            panic!("expected a function");
+        };
+        let StatementKind::Match { arms, .. } = &func.body[0].kind else {
+            panic!("expected match");
+        };
+        for arm in arms {
+            assert!(matches!(&arm.body, ArmBody::Block(body) if matches!(body[0].kind, StatementKind::Return(None))));
+        }
+    }
+
+    #[test]
+    fn match_patterns_and_invalid_pattern() {
+        let result = parse(
+            "func f(s: Status) -> int\n    match s\n        case Status.Banned(r, _) => 1\n        case Active => 2\n        \
+             case _ => 3\n    end\nend\n",
+        );
+        assert!(errors(&result).is_empty());
+        let StatementKind::Match { arms, .. } = &function_body(&result)[0].kind else { panic!("expected a match") };
+        assert!(matches!(
+            &arms[0].pattern,
+            Pattern::Variant { enum_name: Some(e), variant, bindings } if e == "Status" && variant == "Banned" && bindings == &["r", "_"]
+        ));
+        assert!(matches!(&arms[1].pattern, Pattern::Variant { enum_name: None, .. }));
+        assert!(matches!(arms[2].pattern, Pattern::Wildcard));
+
+        let result = parse("func f(s: int)\n    match s\n        case (1) => 1\n    end\nend\n");
+        assert_eq!(errors(&result), [("K0009", 3, 14)]);
+    }
+
+    #[test]
+    fn bare_return_before_else() {
+        let result = parse("func f(c: bool)\n    if c\n        return\n    else\n        f(c)\n    end\nend\n");
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let StatementKind::If { branches, else_body } = &function_body(&result)[0].kind else {
+            panic!("expected if");
+        };
+        assert!(matches!(branches[0].1[0].kind, StatementKind::Return(None)));
+        assert_eq!(else_body.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn bare_return_before_elif() {
+        let result = parse(
+            "func f(a: bool, b: bool)\n    if a\n        return\n    elif b\n        return\n    else if a\n        return\n    end\nend\n",
+        );
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let StatementKind::If { branches, else_body } = &function_body(&result)[0].kind else {
+            panic!("expected if");
+        };
+        assert_eq!(branches.len(), 3);
+        assert!(else_body.is_none());
+        for (_, body) in branches {
+            assert!(matches!(body[0].kind, StatementKind::Return(None)));
+        }
+    }
+
+    #[test]
+    fn else_then_if_on_next_line_nests() {
+        let result = parse("func f(a: bool, b: bool)\n    if a\n        return\n    else\n        if b\n            return\n        end\n    end\nend\n");
+        assert!(errors(&result).is_empty());
+        let StatementKind::If { branches, else_body: Some(else_body) } = &function_body(&result)[0].kind else {
+            panic!("expected if with else");
+        };
+        assert_eq!(branches.len(), 1);
+        assert!(matches!(else_body[0].kind, StatementKind::If { .. }));
+    }
+
+    #[test]
+    fn break_and_continue_need_an_enclosing_loop() {
+        let result = parse("func f()\n    break\n    outer: while true\n        continue inner\n        break outer\n    end\nend\n");
+        assert_eq!(errors(&result), [("K0010", 2, 5), ("K0011", 4, 18)]);
+        let StatementKind::While { label, body, .. } = &function_body(&result)[1].kind else { panic!("expected while") };
+        assert_eq!(label.as_deref(), Some("outer"));
+        assert!(matches!(&body[1].kind, StatementKind::Break(Some(name)) if name == "outer"));
+    }
+
+    #[test]
+    fn for_ranges_and_collections() {
+        let result = parse(
+            "func f()\n    for i = 0, 10, 2\n    end\n    for i = 10 to 0\n    end\n    for k, v in m\n    end\n    for i to 3\n    end\nend\n",
+        );
+        let body = function_body(&result);
+        assert!(matches!(
+            &body[0].kind,
+            StatementKind::For { iter: ForIter::Range { step: Some(_), inclusive: false, .. }, .. }
+        ));
+        assert!(matches!(&body[1].kind, StatementKind::For { iter: ForIter::Range { step: None, inclusive: true, .. }, .. }));
+        assert!(matches!(
+            &body[2].kind,
+            StatementKind::For { var, iter: ForIter::Each { value: Some(value), .. }, .. } if var == "k" && value == "v"
+        ));
+        assert_eq!(errors(&result), [("K0001", 8, 11)]);
+    }
+
+    #[test]
+    fn error_code_declarations() {
+        let result = parse("error_code NotFound = 404\nerror_code BadInput = 400 \"bad input\"\nerror_code Teapot = 99\n");
+        assert_eq!(errors(&result), [("K0012", 3, 21)]);
+        let TopLevel::ErrorCode(bad_input) = &result.items[1] else { panic!("expected an error code") };
+        assert_eq!((bad_input.status, bad_input.message.as_deref()), (400, Some("bad input")));
+    }
+
+    #[test]
+    fn bare_return_before_catch_and_finally() {
+        let result = parse("func f()\n    try\n        return\n    catch err\n        return\n    finally\n        f()\n    end\nend\n");
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let StatementKind::Try { body, catches, finally } = &function_body(&result)[0].kind else {
+            panic!("expected try");
+        };
+        assert!(matches!(body[0].kind, StatementKind::Return(None)));
+        assert!(matches!(catches[0].body[0].kind, StatementKind::Return(None)));
+        assert_eq!(finally.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn catch_with_single_error_code() {
+        let result = parse(
+            "error_code not_found = 404\n\
+             func f()\n    try\n        f()\n    catch not_found\n        f()\n    catch Banned\n        f()\n    catch err\n        f()\n    end\nend\n",
+        );
+        assert!(!result.has_errors(), "{:?}", result.diagnostics);
+        let Some(TopLevel::Function(func)) = result.items.get(1) else {
+            panic!("expected a function");
+        };
+        let StatementKind::Try { catches, .. } = &func.body[0].kind else {
+            panic!("expected try");
+        };
+        let clauses: Vec<_> = catches.iter().map(|clause| (clause.code.as_deref(), clause.var.as_deref())).collect();
+        assert_eq!(clauses, [(Some("not_found"), None), (Some("Banned"), None), (None, Some("err"))]);
+    }
+
+    #[test]
+    fn optional_types_nil_and_coalesce() {
+        let result = parse("func f(x: list<int>?) -> int?\n    return nil\nend\n");
+        assert!(errors(&result).is_empty());
+        let TopLevel::Function(function) = &result.items[0] else { panic!("expected a function") };
+        assert_eq!(function.params[0].1.to_rust(), "Option<Vec<i32>>");
+        assert!(matches!(returned("func f()\n    return nil\nend\n").kind, ExprKind::Nil));
+        // `??` сильнее сравнений, слабее арифметики и правоассоциативен
+        let value = returned("func f()\n    return a ?? b ?? c + e == d\nend\n");
+        assert_eq!(shape(&value), "((a ?? (b ?? (c + e))) == d)");
+    }
 }
@@ -0,0 +1,457 @@
+// Разрешение пользовательских типов
+//
+// Парсер записывает любое имя в позиции типа как `DataType::Named`, не зная,
+// что за ним стоит. Этот проход проверяет, что имя объявлено (моделью,
+// перечислением или псевдонимом), и подставляет вместо псевдонимов их
+// определения: кодогенератор видит уже готовые `list<User>`, `int?` и т.п.
+
+use super::check::closest;
+use super::span::Span;
+use super::parser::{
+    ArmBody, DataType, EnumDef, Expr, ExprKind, ForIter, InterpolationPart, MatchArm, ModelDef, Pattern, Statement, StatementKind,
+    TopLevel,
+};
+use crate::modules::diagnostics::Diagnostic;
+use std::collections::{HashMap, HashSet};
+
+/// Встроенные типы — для подсказок при опечатке
+const BUILTIN_TYPES: &[&str] = &["int", "float", "double", "txt", "bool", "auto", "json", "list", "map"];
+
+/// Разрешает имена типов в программе и возвращает найденные ошибки
+pub fn resolve(items: &mut [TopLevel]) -> Vec<Diagnostic> {
+    let mut resolver = Resolver::new(items);
+    for item in items.iter_mut() {
+        resolver.resolve_item(item);
+    }
+    resolver.diagnostics.extend(recursive_types(items));
+    resolver.diagnostics
+}
+
+struct Resolver {
+    /// Модели и перечисления: их имена в Rust совпадают с именами в Krait
+    types: HashSet<String>,
+    /// Псевдонимы в том виде, как они записаны
+    aliases: HashMap<String, DataType>,
+    /// Раскрытые псевдонимы; `None` — раскрыть не удалось (ошибка уже записана)
+    expanded: HashMap<String, Option<DataType>>,
+    /// Псевдонимы, которые раскрываются прямо сейчас: повтор означает цикл
+    expanding: Vec<String>,
+    /// Импортированные модули: `utils.User` ссылается на тип из `utils`
+    modules: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn new(items: &[TopLevel]) -> Self {
+        let mut resolver = Resolver {
+            types: HashSet::new(),
+            aliases: HashMap::new(),
+            expanded: HashMap::new(),
+            expanding: Vec::new(),
+            modules: HashSet::new(),
+            diagnostics: Vec::new(),
+        };
+        for item in items {
+            match item {
+                TopLevel::Model(model) => {
+                    resolver.types.insert(model.name.clone());
+                }
+                TopLevel::Enum(enum_def) => {
+                    resolver.types.insert(enum_def.name.clone());
+                }
+                TopLevel::TypeAlias(alias) => {
+                    resolver.aliases.insert(alias.name.clone(), alias.target.clone());
+                }
+                TopLevel::Import { module, .. } => {
+                    resolver.modules.insert(module.clone());
+                }
+                _ => {}
+            }
+        }
+        resolver
+    }
+
+    fn resolve_item(&mut self, item: &mut TopLevel) {
+        match item {
+            TopLevel::Function(func) => {
+                for (_, ty) in &mut func.params {
+                    self.resolve_type(ty);
+                }
+                self.resolve_type(&mut func.return_type);
+                self.resolve_block(&mut func.body);
+            }
+            TopLevel::Route(route) => self.resolve_block(&mut route.body),
+            TopLevel::Model(model) => {
+                for field in &mut model.fields {
+                    self.resolve_type(&mut field.field_type);
+                    if let Some(default) = &mut field.default {
+                        self.resolve_expr(default);
+                    }
+                }
+            }
+            TopLevel::Enum(enum_def) => {
+                for variant in &mut enum_def.variants {
+                    for (_, ty) in &mut variant.fields {
+                        self.resolve_type(ty);
+                    }
+                }
+            }
+            TopLevel::TypeAlias(alias) => {
+                if let Some(Some(target)) = self.expand(&alias.name, alias.span) {
+                    alias.target = target;
+                }
+            }
+            TopLevel::Statement(stmt) => self.resolve_statement(stmt),
+            TopLevel::ErrorCode(_) | TopLevel::Import { .. } | TopLevel::Error(_) => {}
+        }
+    }
+
+    fn resolve_block(&mut self, body: &mut [Statement]) {
+        for stmt in body {
+            self.resolve_statement(stmt);
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &mut Statement) {
+        match &mut stmt.kind {
+            StatementKind::VarDecl { var_type, value, .. } => {
+                self.resolve_type(var_type);
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+            StatementKind::If { branches, else_body } => {
+                for (condition, body) in branches {
+                    self.resolve_expr(condition);
+                    self.resolve_block(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.resolve_block(else_body);
+                }
+            }
+            StatementKind::While { condition, body, .. } => {
+                self.resolve_expr(condition);
+                self.resolve_block(body);
+            }
+            StatementKind::For { iter, body, .. } => {
+                match iter {
+                    ForIter::Range { start, end, step, .. } => {
+                        self.resolve_expr(start);
+                        self.resolve_expr(end);
+                        if let Some(step) = step {
+                            self.resolve_expr(step);
+                        }
+                    }
+                    ForIter::Each { collection, .. } => self.resolve_expr(collection),
+                }
+                self.resolve_block(body);
+            }
+            StatementKind::Try { body, catches, finally } => {
+                self.resolve_block(body);
+                for clause in catches {
+                    self.resolve_block(&mut clause.body);
+                }
+                if let Some(finally) = finally {
+                    self.resolve_block(finally);
+                }
+            }
+            StatementKind::ExprStmt(expr) => self.resolve_expr(expr),
+            StatementKind::Assign { target, value, .. } => {
+                self.resolve_expr(target);
+                self.resolve_expr(value);
+            }
+            StatementKind::Match { scrutinee, arms } => {
+                self.resolve_expr(scrutinee);
+                self.resolve_arms(arms);
+            }
+            StatementKind::Break(_) | StatementKind::Continue(_) | StatementKind::Raise(_) | StatementKind::Error => {}
+        }
+    }
+
+    fn resolve_arms(&mut self, arms: &mut [MatchArm]) {
+        for arm in arms {
+            if let Pattern::Literal(value) = &mut arm.pattern {
+                self.resolve_expr(value);
+            }
+            match &mut arm.body {
+                ArmBody::Block(body) => self.resolve_block(body),
+                ArmBody::Value(value) => self.resolve_expr(value),
+            }
+        }
+    }
+
+    /// Типы в выражениях встречаются только внутри блоков веток `match`
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::BinaryOp { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            ExprKind::Unary { operand: target, .. } | ExprKind::Field { target, .. } => self.resolve_expr(target),
+            ExprKind::FunctionCall { args: items, .. } | ExprKind::List(items) => {
+                items.iter_mut().for_each(|e| self.resolve_expr(e));
+            }
+            ExprKind::MethodCall { target, args, .. } => {
+                self.resolve_expr(target);
+                args.iter_mut().for_each(|e| self.resolve_expr(e));
+            }
+            ExprKind::Index { target, index } => {
+                self.resolve_expr(target);
+                self.resolve_expr(index);
+            }
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            ExprKind::Construct { fields, .. } => fields.iter_mut().for_each(|(_, e)| self.resolve_expr(e)),
+            ExprKind::Match { scrutinee, arms } => {
+                self.resolve_expr(scrutinee);
+                self.resolve_arms(arms);
+            }
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(e) = part {
+                        self.resolve_expr(e);
+                    }
+                }
+            }
+            ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Nil | ExprKind::Error => {}
+        }
+    }
+
+    /// Проверяет имена в типе и раскрывает псевдонимы
+    fn resolve_type(&mut self, ty: &mut DataType) {
+        match ty {
+            DataType::List(inner) | DataType::Optional(inner) => self.resolve_type(inner),
+            DataType::Map(key, value) => {
+                self.resolve_type(key);
+                self.resolve_type(value);
+            }
+            DataType::Named { name, span } => {
+                if self.types.contains(name.as_str()) {
+                    return;
+                }
+                if let Some(expanded) = self.expand(name, *span) {
+                    if let Some(target) = expanded {
+                        *ty = target;
+                    }
+                    return;
+                }
+                // Типы других файлов проекта проверит rustc: здесь их объявлений не видно
+                if let Some((module, _)) = name.split_once('.') {
+                    if self.modules.contains(module) {
+                        return;
+                    }
+                    let diag = Diagnostic::error("K0214", format!("cannot find module `{}`", module), *span)
+                        .with_help(format!("import it first: `import {} from {}`", module, module));
+                    self.diagnostics.push(diag);
+                    return;
+                }
+                let diag = Diagnostic::error("K0214", format!("cannot find type `{}`", name), *span);
+                let candidates = BUILTIN_TYPES
+                    .iter()
+                    .copied()
+                    .chain(self.types.iter().map(String::as_str))
+                    .chain(self.aliases.keys().map(String::as_str));
+                let diag = if let Some(builtin) = builtin_synonym(name) {
+                    diag.with_help(format!("use the built-in type `{}`", builtin))
+                } else {
+                    match closest(name, candidates) {
+                        Some(suggestion) => diag.with_help(format!("a type with a similar name exists: `{}`", suggestion)),
+                        None => diag.with_help(format!("declare it as a `model`, an `enum` or `type {} = ...`", name)),
+                    }
+                };
+                self.diagnostics.push(diag);
+            }
+            DataType::Int | DataType::Float | DataType::Double | DataType::Txt | DataType::Bool | DataType::Auto
+            | DataType::Json => {}
+        }
+    }
+
+    /// Определение псевдонима с раскрытыми именами; `None`, если `name` — не псевдоним.
+    /// `span` — место, где псевдоним упомянут: там сообщается о цикле.
+    fn expand(&mut self, name: &str, span: Span) -> Option<Option<DataType>> {
+        if let Some(expanded) = self.expanded.get(name) {
+            return Some(expanded.clone());
+        }
+        let mut target = self.aliases.get(name)?.clone();
+        if let Some(position) = self.expanding.iter().position(|alias| alias == name) {
+            let cycle: Vec<&str> = self.expanding[position..].iter().map(String::as_str).chain([name]).collect();
+            let diag = Diagnostic::error("K0215", format!("type alias `{}` refers to itself", name), span)
+                .with_note(format!("the cycle is `{}`", cycle.join("` → `")))
+                .with_help("a type alias cannot be recursive; declare a `model` instead");
+            self.diagnostics.push(diag);
+            return Some(None);
+        }
+        self.expanding.push(name.to_string());
+        let errors = self.diagnostics.len();
+        self.resolve_type(&mut target);
+        self.expanding.pop();
+        let expanded = (self.diagnostics.len() == errors).then_some(target);
+        self.expanded.insert(name.to_string(), expanded.clone());
+        Some(expanded)
+    }
+}
+
+/// Модели и перечисления, которые хранят сами себя напрямую или через `T?`: в Rust
+/// у такого типа бесконечный размер. `list<T>` и `map<K, V>` держат значения в куче,
+/// поэтому цикл через них допустим.
+fn recursive_types(items: &[TopLevel]) -> Vec<Diagnostic> {
+    // Тип → типы, которые он хранит по значению, и места полей
+    let mut stored: HashMap<&str, Vec<(&str, Span)>> = HashMap::new();
+    for item in items {
+        match item {
+            TopLevel::Model(model) => {
+                let fields = model.fields.iter().filter_map(|field| Some((stored_type(&field.field_type)?, field.span)));
+                stored.insert(&model.name, fields.collect());
+            }
+            TopLevel::Enum(enum_def) => {
+                let fields = enum_def
+                    .variants
+                    .iter()
+                    .flat_map(|variant| variant.fields.iter().filter_map(|(_, ty)| Some((stored_type(ty)?, variant.span))));
+                stored.insert(&enum_def.name, fields.collect());
+            }
+            _ => {}
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut visited = HashSet::new();
+    for item in items {
+        if let TopLevel::Model(ModelDef { name, .. }) | TopLevel::Enum(EnumDef { name, .. }) = item {
+            find_cycles(name, &stored, &mut Vec::new(), &mut visited, &mut diagnostics);
+        }
+    }
+    diagnostics
+}
+
+/// Обход в глубину от `name`; `path` — типы, обход которых ещё не закончен
+fn find_cycles<'a>(
+    name: &'a str,
+    stored: &HashMap<&'a str, Vec<(&'a str, Span)>>,
+    path: &mut Vec<&'a str>,
+    visited: &mut HashSet<&'a str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if !visited.insert(name) {
+        return;
+    }
+    path.push(name);
+    for &(next, span) in stored.get(name).into_iter().flatten() {
+        if let Some(position) = path.iter().position(|seen| *seen == next) {
+            let cycle: Vec<&str> = path[position..].iter().copied().chain([next]).collect();
+            let diag = Diagnostic::error("K0222", format!("recursive type `{}` has infinite size", next), span)
+                .with_note(format!("the cycle is `{}`", cycle.join("` → `")))
+                .with_help(format!("store the nested value in a list instead: `list<{}>`", next));
+            diagnostics.push(diag);
+        } else {
+            find_cycles(next, stored, path, visited, diagnostics);
+        }
+    }
+    path.pop();
+}
+
+/// Пользовательский тип, значение которого поле хранит прямо в себе
+fn stored_type(ty: &DataType) -> Option<&str> {
+    match ty {
+        DataType::Named { name, .. } => Some(name),
+        DataType::Optional(inner) => stored_type(inner),
+        _ => None,
+    }
+}
+
+/// Привычные по другим языкам названия встроенных типов
+fn builtin_synonym(name: &str) -> Option<&'static str> {
+    match name {
+        "string" | "String" | "str" => Some("txt"),
+        "boolean" => Some("bool"),
+        "integer" | "i32" => Some("int"),
+        "f32" => Some("float"),
+        "f64" => Some("double"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::codegen::lexer::Lexer;
+    use crate::modules::codegen::parser::Parser;
+
+    fn resolve_source(source: &str) -> (Vec<TopLevel>, Vec<Diagnostic>) {
+        let tokens = Lexer::new(source).tokenize();
+        let mut parsed = Parser::new(tokens).parse();
+        assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+        let diagnostics = resolve(&mut parsed.items);
+        (parsed.items, diagnostics)
+    }
+
+    fn helps(diagnostics: &[Diagnostic]) -> Vec<(&'static str, Option<&str>)> {
+        diagnostics.iter().map(|d| (d.code, d.help.as_deref())).collect()
+    }
+
+    #[test]
+    fn short_unknown_names_get_no_unrelated_suggestion() {
+        let (_, diagnostics) = resolve_source("model User\n    txt name\nend\nfunc f(a: Foo, b: Bar, c: Usr)\nend\n");
+        assert_eq!(
+            helps(&diagnostics),
+            [
+                ("K0214", Some("declare it as a `model`, an `enum` or `type Foo = ...`")),
+                ("K0214", Some("declare it as a `model`, an `enum` or `type Bar = ...`")),
+                ("K0214", Some("a type with a similar name exists: `User`")),
+            ]
+        );
+    }
+
+    #[test]
+    fn aliases_expand_to_their_targets() {
+        let (items, diagnostics) =
+            resolve_source("model User\n    txt name\nend\ntype Users = list<User>\ntype Id = int\nfunc f(a: Users, b: Id?)\nend\n");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Some(TopLevel::Function(function)) = items.last() else { panic!("expected a function") };
+        assert_eq!(function.params[0].1.to_rust(), "Vec<User>");
+        assert_eq!(function.params[1].1.to_rust(), "Option<i32>");
+    }
+
+    #[test]
+    fn alias_cycle_and_builtin_synonym() {
+        let (_, diagnostics) = resolve_source("type A = list<B>\ntype B = A\nfunc f(x: A, y: string)\nend\n");
+        assert_eq!(
+            helps(&diagnostics),
+            [
+                ("K0215", Some("a type alias cannot be recursive; declare a `model` instead")),
+                ("K0214", Some("use the built-in type `txt`")),
+            ]
+        );
+        assert_eq!((diagnostics[0].span.line, diagnostics[0].span.column), (2, 10));
+        assert_eq!(diagnostics[0].notes, ["the cycle is `A` → `B` → `A`"]);
+        assert_eq!((diagnostics[1].span.line, diagnostics[1].span.column), (3, 17));
+    }
+
+    #[test]
+    fn recursive_models_are_reported_once_per_cycle() {
+        let (_, diagnostics) = resolve_source(
+            "model Node\n    int value\n    Node? next\nend\nmodel A\n    B b\nend\ntype Link = A?\nmodel B\n    Link a\nend\n\
+             model Tree\n    list<Tree> children\n    map<txt, Tree> named\nend\n",
+        );
+        assert_eq!(
+            helps(&diagnostics),
+            [
+                ("K0222", Some("store the nested value in a list instead: `list<Node>`")),
+                ("K0222", Some("store the nested value in a list instead: `list<A>`")),
+            ]
+        );
+        assert_eq!(diagnostics[0].span.line, 3);
+        assert_eq!(diagnostics[0].notes, ["the cycle is `Node` → `Node`"]);
+        assert_eq!(diagnostics[1].span.line, 10);
+        assert_eq!(diagnostics[1].notes, ["the cycle is `A` → `B` → `A`"]);
+    }
+}
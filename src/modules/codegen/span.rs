@@ -0,0 +1,75 @@
+// Позиции в исходном коде Krait
+
+use std::fmt;
+
+/// Участок исходного файла: байтовые смещения и строка/колонка начала и конца
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file_id: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Объединяет два участка: от начала `self` до конца `other`
+    pub fn to(&self, other: Span) -> Span {
+        // Перевёрнутое объединение схлопывается в пустой участок в начале `self`
+        let (end, end_line, end_column) = if other.end < self.start {
+            (self.start, self.line, self.column)
+        } else {
+            (other.end, other.end_line, other.end_column)
+        };
+        Span {
+            file_id: self.file_id,
+            start: self.start,
+            end,
+            line: self.line,
+            column: self.column,
+            end_line,
+            end_column,
+        }
+    }
+
+    /// Длина участка в байтах
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_joins_start_of_first_and_end_of_second() {
+        let a = Span { file_id: 0, start: 4, end: 8, line: 1, column: 5, end_line: 1, end_column: 9 };
+        let b = Span { file_id: 0, start: 20, end: 23, line: 3, column: 1, end_line: 3, end_column: 4 };
+        let joined = a.to(b);
+        assert_eq!((joined.start, joined.end, joined.len()), (4, 23, 19));
+        assert_eq!((joined.line, joined.column, joined.end_line, joined.end_column), (1, 5, 3, 4));
+        assert_eq!(joined.to_string(), "1:5");
+    }
+
+    #[test]
+    fn to_never_ends_before_start() {
+        let a = Span { start: 10, end: 12, line: 2, column: 3, end_line: 2, end_column: 5, ..Span::default() };
+        let b = Span { start: 0, end: 2, line: 1, column: 1, end_line: 1, end_column: 3, ..Span::default() };
+        let joined = a.to(b);
+        assert!(joined.is_empty());
+        assert_eq!((joined.line, joined.column, joined.end_line, joined.end_column), (2, 3, 2, 3));
+    }
+}
@@ -0,0 +1,160 @@
+// Диагностики транслятора: ошибки и предупреждения с позицией в исходнике
+
+pub mod render;
+
+pub use render::Renderer;
+
+use super::codegen::error::{LexError, ParseError};
+use super::codegen::lexer::TokenType;
+use super::codegen::span::Span;
+use std::fmt;
+
+/// Серьёзность диагностики
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Дополнительный участок кода с подписью
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Сообщение об ошибке или предупреждение
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Self::error(code, message, span)
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}: {}", self.severity, self.code, self.span, self.message)
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let diag = Diagnostic::error(err.code(), err.to_string(), err.span());
+        match &err {
+            ParseError::ChainedComparison { .. } => diag.with_help("split the comparison with `and`: `a < b and b < c`"),
+            ParseError::ExpectedExpression { found: TokenType::Else | TokenType::Elif, .. } => {
+                diag.with_note("`elif` and `else` continue an `if` before its `end`; `else` must be the last branch")
+            }
+            ParseError::OutsideLoop { .. } => diag.with_note("`break` and `continue` can only be used inside `while` or `for`"),
+            ParseError::UndeclaredLabel { label, .. } => {
+                diag.with_help(format!("label the enclosing loop: `{}: while ...`", label))
+            }
+            ParseError::ExpectedPattern { .. } => {
+                diag.with_note("a pattern is a literal, an enum variant such as `Status.Active`, or `_`")
+            }
+            ParseError::InvalidStatus { .. } => {
+                diag.with_note("an error code is raised as an HTTP response, so its status must be 400–599")
+            }
+            _ => diag,
+        }
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        let diag = Diagnostic::error(err.code(), err.to_string(), err.span());
+        match &err {
+            LexError::UnexpectedChar { ch: ';', .. } => {
+                diag.with_help("Krait statements end at the line break, remove the `;`")
+            }
+            LexError::UnexpectedChar { ch: '&', .. } => diag.with_help("use `&&` or `and` for logical AND"),
+            LexError::UnexpectedChar { ch: '|', .. } => diag.with_help("use `||` or `or` for logical OR"),
+            LexError::UnterminatedString { .. } => {
+                diag.with_help("add the closing quote; strings that span lines use `\"\"\"...\"\"\"`")
+            }
+            LexError::UnterminatedComment { .. } => {
+                diag.with_note("block comments nest: every `/*` needs its own `*/`")
+            }
+            LexError::UnterminatedInterpolation { .. } => {
+                diag.with_help("close the interpolation with `}` or write `\\{` for a literal brace")
+            }
+            LexError::EmptyInterpolation { .. } => {
+                diag.with_help("put an expression inside, such as `{name}`, or write `\\{}` for literal braces")
+            }
+            LexError::InvalidEscape { .. } => {
+                diag.with_note("valid escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\'`, `\\{` and `\\}`")
+            }
+            _ => diag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize, column: usize) -> Span {
+        Span { line, column, ..Span::default() }
+    }
+
+    #[test]
+    fn lex_errors_keep_code_and_span() {
+        let diag = Diagnostic::from(LexError::UnexpectedChar { span: span(4, 9), ch: ';' });
+        assert_eq!((diag.code, diag.span, diag.message.as_str()), ("K0101", span(4, 9), "unexpected character `;`"));
+        assert_eq!(diag.help.as_deref(), Some("Krait statements end at the line break, remove the `;`"));
+        assert_eq!(diag.to_string(), "error[K0101]: 4:9: unexpected character `;`");
+    }
+}
@@ -0,0 +1,257 @@
+// Вывод диагностик в стиле rustc: строка исходника, подчёркивание, подписи
+
+use super::{Diagnostic, Severity};
+use std::io::IsTerminal;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Включать ли цвет: только если stderr — терминал и не задан `NO_COLOR`
+pub fn color_enabled() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Отметка под строкой исходника
+struct Mark<'a> {
+    line: usize,
+    column: usize,
+    width: usize,
+    primary: bool,
+    message: &'a str,
+}
+
+/// Печатает диагностики для одного файла
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// Цвет определяется автоматически (см. `color_enabled`)
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Renderer {
+            file_name,
+            source,
+            color: color_enabled(),
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.source.lines().nth(line.saturating_sub(1)).unwrap_or("")
+    }
+
+    /// Ширина префикса строки до колонки `column` (табуляция — 4 пробела)
+    fn display_offset(line: &str, column: usize) -> usize {
+        line.chars()
+            .take(column.saturating_sub(1))
+            .map(|ch| if ch == '\t' { 4 } else { 1 })
+            .sum()
+    }
+
+    /// Отображает одну диагностику
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let style = Self::severity_style(diag.severity);
+        let mut out = String::new();
+
+        out.push_str(&self.paint(style, &format!("{}[{}]", diag.severity, diag.code)));
+        out.push_str(&self.paint(BOLD, &format!(": {}", diag.message)));
+        out.push('\n');
+
+        let mut marks = vec![Mark {
+            line: diag.span.line,
+            column: diag.span.column,
+            width: mark_width(diag.span.line, diag.span.column, diag.span.end_line, diag.span.end_column),
+            primary: true,
+            message: "",
+        }];
+        for label in &diag.labels {
+            marks.push(Mark {
+                line: label.span.line,
+                column: label.span.column,
+                width: mark_width(label.span.line, label.span.column, label.span.end_line, label.span.end_column),
+                primary: false,
+                message: &label.message,
+            });
+        }
+        marks.sort_by_key(|m| (m.line, m.column));
+
+        let gutter = marks.iter().map(|m| m.line).max().unwrap_or(1).to_string().len();
+        let pad = " ".repeat(gutter);
+        let bar = self.paint(BLUE, "|");
+
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            pad,
+            self.paint(BLUE, "-->"),
+            self.file_name,
+            diag.span.line,
+            diag.span.column
+        ));
+        out.push_str(&format!("{} {}\n", pad, bar));
+
+        let mut previous_line = None;
+        for mark in &marks {
+            let text = self.source_line(mark.line);
+            if previous_line != Some(mark.line) {
+                if previous_line.is_some_and(|prev| mark.line > prev + 1) {
+                    out.push_str(&format!("{}\n", self.paint(BLUE, "...")));
+                }
+                let number = format!("{:>width$}", mark.line, width = gutter);
+                out.push_str(&format!(
+                    "{} {} {}\n",
+                    self.paint(BLUE, &number),
+                    bar,
+                    text.replace('\t', "    ")
+                ));
+                previous_line = Some(mark.line);
+            }
+
+            let (symbol, mark_style) = if mark.primary { ('^', style) } else { ('-', BLUE) };
+            let underline = symbol.to_string().repeat(mark.width);
+            let mut annotation = format!(
+                "{}{}",
+                " ".repeat(Self::display_offset(text, mark.column)),
+                self.paint(mark_style, &underline)
+            );
+            if !mark.message.is_empty() {
+                annotation.push(' ');
+                annotation.push_str(&self.paint(mark_style, mark.message));
+            }
+            out.push_str(&format!("{} {} {}\n", pad, bar, annotation));
+        }
+
+        if !diag.notes.is_empty() || diag.help.is_some() {
+            out.push_str(&format!("{} {}\n", pad, bar));
+        }
+        for note in &diag.notes {
+            out.push_str(&format!("{} {} {}: {}\n", pad, self.paint(BLUE, "="), self.paint(BOLD, "note"), note));
+        }
+        if let Some(help) = &diag.help {
+            out.push_str(&format!("{} {} {}: {}\n", pad, self.paint(BLUE, "="), self.paint(BOLD, "help"), help));
+        }
+
+        out
+    }
+
+    /// Отображает все диагностики и итоговую строку с количеством ошибок
+    pub fn render_all(&self, diagnostics: &[Diagnostic]) -> String {
+        let mut out = String::new();
+        for diag in diagnostics {
+            out.push_str(&self.render(diag));
+            out.push('\n');
+        }
+        let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+        if errors > 0 {
+            let summary = if errors == 1 {
+                "aborting due to 1 previous error".to_string()
+            } else {
+                format!("aborting due to {} previous errors", errors)
+            };
+            out.push_str(&self.paint(RED, "error"));
+            out.push_str(&self.paint(BOLD, &format!(": {}", summary)));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Длина подчёркивания: весь участок, если он на одной строке, иначе один символ
+fn mark_width(line: usize, column: usize, end_line: usize, end_column: usize) -> usize {
+    if end_line == line && end_column > column {
+        end_column - column
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::codegen::span::Span;
+
+    const SOURCE: &str = "func f(a: int) -> int\n    return a + b\nend\n";
+
+    fn span(line: usize, column: usize, end_column: usize) -> Span {
+        Span { line, column, end_line: line, end_column, ..Span::default() }
+    }
+
+    #[test]
+    fn renders_snippet_with_caret_under_span() {
+        let diag = Diagnostic::error("K0201", "cannot find `b`", span(2, 16, 17)).with_help("declare `b` first");
+        let out = Renderer::new("main.kr", SOURCE).with_color(false).render(&diag);
+        assert_eq!(
+            out,
+            "error[K0201]: cannot find `b`\n \
+             --> main.kr:2:16\n  \
+             |\n\
+             2 |     return a + b\n  \
+             |                ^\n  \
+             |\n  \
+             = help: declare `b` first\n"
+        );
+    }
+
+    #[test]
+    fn renders_secondary_labels_and_notes() {
+        let diag = Diagnostic::warning("K0212", "unreachable", span(2, 12, 17))
+            .with_label(span(1, 8, 9), "declared here")
+            .with_note("first note");
+        let out = Renderer::new("main.kr", SOURCE).with_color(false).render(&diag);
+        assert_eq!(
+            out,
+            "warning[K0212]: unreachable\n \
+             --> main.kr:2:12\n  \
+             |\n\
+             1 | func f(a: int) -> int\n  \
+             |        - declared here\n\
+             2 |     return a + b\n  \
+             |            ^^^^^\n  \
+             |\n  \
+             = note: first note\n"
+        );
+    }
+
+    #[test]
+    fn color_is_optional() {
+        let diag = Diagnostic::error("K0001", "bad", span(1, 1, 5));
+        let plain = Renderer::new("main.kr", SOURCE).with_color(false).render(&diag);
+        let colored = Renderer::new("main.kr", SOURCE).with_color(true).render(&diag);
+        assert!(!plain.contains('\x1b'));
+        assert!(colored.contains(RED) && colored.contains(RESET));
+    }
+
+    #[test]
+    fn render_all_ends_with_error_count() {
+        let diagnostics = [
+            Diagnostic::error("K0001", "one", span(1, 1, 2)),
+            Diagnostic::warning("K0002", "two", span(2, 1, 2)),
+            Diagnostic::error("K0003", "three", span(3, 1, 2)),
+        ];
+        let out = Renderer::new("main.kr", SOURCE).with_color(false).render_all(&diagnostics);
+        assert!(out.ends_with("error: aborting due to 2 previous errors\n"), "{}", out);
+    }
+}
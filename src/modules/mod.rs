@@ -3,8 +3,10 @@
 pub mod codegen;
 pub mod cli;
 pub mod api;
+pub mod diagnostics;
 
 // Реэкспортируем типы из codegen
-pub use codegen::{Lexer, Parser, CodeGenerator};
+pub use codegen::{Lexer, Parser, ParseResult, CodeGenerator, Span, LexError, ParseError, TranslateError};
 pub use codegen::lexer::TokenType;
-pub use codegen::parser::{TopLevel, DataType, Expr, Statement, FunctionDef, RouteDef};
+pub use diagnostics::{Diagnostic, Severity};
+pub use codegen::parser::{TopLevel, DataType, Assoc, BinOp, Expr, ExprKind, UnaryOp, InterpolationPart, Literal, Statement, StatementKind, ForIter, FunctionDef, RouteDef, ModelDef, FieldDef, EnumDef, VariantDef, ErrorCodeDef, TypeAliasDef, CatchClause, MatchArm, ArmBody, Pattern};